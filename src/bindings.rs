@@ -0,0 +1,278 @@
+//! Keyboard bindings for logical input actions, loaded from and saved to the
+//! user's config directory.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+use ggez::event::KeyCode;
+use ggez::{filesystem, Context};
+
+use config::{self, ConfigError};
+use resources::Action;
+
+const BINDINGS_FILE: &str = "bindings.cfg";
+
+/// Keys which may be bound to actions, in the order they are matched by name.
+const BINDABLE_KEYS: &[KeyCode] = &[
+    KeyCode::A,
+    KeyCode::B,
+    KeyCode::C,
+    KeyCode::D,
+    KeyCode::E,
+    KeyCode::F,
+    KeyCode::G,
+    KeyCode::H,
+    KeyCode::I,
+    KeyCode::J,
+    KeyCode::K,
+    KeyCode::L,
+    KeyCode::M,
+    KeyCode::N,
+    KeyCode::O,
+    KeyCode::P,
+    KeyCode::Q,
+    KeyCode::R,
+    KeyCode::S,
+    KeyCode::T,
+    KeyCode::U,
+    KeyCode::V,
+    KeyCode::W,
+    KeyCode::X,
+    KeyCode::Y,
+    KeyCode::Z,
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+    KeyCode::F1,
+    KeyCode::F2,
+    KeyCode::F3,
+    KeyCode::F4,
+    KeyCode::F5,
+    KeyCode::F6,
+    KeyCode::F7,
+    KeyCode::F8,
+    KeyCode::F9,
+    KeyCode::F10,
+    KeyCode::F11,
+    KeyCode::F12,
+    KeyCode::Left,
+    KeyCode::Up,
+    KeyCode::Right,
+    KeyCode::Down,
+    KeyCode::Return,
+    KeyCode::Space,
+    KeyCode::Escape,
+    KeyCode::Tab,
+    KeyCode::Back,
+    KeyCode::Insert,
+    KeyCode::Delete,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::LShift,
+    KeyCode::RShift,
+    KeyCode::LControl,
+    KeyCode::RControl,
+    KeyCode::Comma,
+    KeyCode::Period,
+    KeyCode::Slash,
+    KeyCode::Semicolon,
+    KeyCode::Apostrophe,
+    KeyCode::LBracket,
+    KeyCode::RBracket,
+    KeyCode::Minus,
+    KeyCode::Equals,
+];
+
+pub fn key_name(key: KeyCode) -> String {
+    format!("{:?}", key)
+}
+
+fn key_from_name(name: &str) -> Option<KeyCode> {
+    BINDABLE_KEYS
+        .iter()
+        .cloned()
+        .find(|key| key_name(*key) == name)
+}
+
+pub struct Bindings {
+    keys: HashMap<Action, Vec<KeyCode>>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let mut keys = HashMap::new();
+        keys.insert(Action::CursorUp, vec![KeyCode::Up]);
+        keys.insert(Action::CursorDown, vec![KeyCode::Down]);
+        keys.insert(Action::CursorLeft, vec![KeyCode::Left]);
+        keys.insert(Action::CursorRight, vec![KeyCode::Right]);
+        keys.insert(Action::CameraUp, vec![KeyCode::W]);
+        keys.insert(Action::CameraDown, vec![KeyCode::S]);
+        keys.insert(Action::CameraLeft, vec![KeyCode::A]);
+        keys.insert(Action::CameraRight, vec![KeyCode::D]);
+        keys.insert(Action::ZoomIn, vec![KeyCode::Equals]);
+        keys.insert(Action::ZoomOut, vec![KeyCode::Minus]);
+        keys.insert(Action::FitPitch, vec![KeyCode::Tab]);
+        keys.insert(Action::Select, vec![KeyCode::Return, KeyCode::Space]);
+        keys.insert(Action::Cancel, vec![KeyCode::Escape]);
+        keys.insert(Action::EndTurn, vec![KeyCode::F5]);
+        keys.insert(Action::LoadTurn, vec![KeyCode::F9]);
+        keys.insert(Action::Bindings, vec![KeyCode::F1]);
+        Self { keys }
+    }
+}
+
+impl Bindings {
+    pub fn path(ctx: &Context) -> PathBuf {
+        filesystem::user_config_dir(ctx).join(BINDINGS_FILE)
+    }
+
+    /// Loads bindings from `path`, falling back to the defaults for any
+    /// actions which the file does not mention.
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        let contents = config::read_file(path)?;
+        let mut bindings = Self::default();
+
+        for entry in config::parse(&contents)? {
+            let action =
+                Action::from_name(entry.name).ok_or_else(|| ConfigError::unknown_field(&entry))?;
+            let keys = entry
+                .value
+                .split(',')
+                .map(str::trim)
+                .filter(|name| !name.is_empty())
+                .map(key_from_name)
+                .collect::<Option<Vec<_>>>()
+                .ok_or_else(|| ConfigError::invalid_value(&entry))?;
+            // There must always be a way back into the bindings menu
+            if action == Action::Bindings && keys.is_empty() {
+                return Err(ConfigError::invalid_value(&entry));
+            }
+            bindings.keys.insert(action, keys);
+        }
+
+        Ok(bindings)
+    }
+
+    pub fn save(&self, path: &Path) -> io::Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut file = File::create(path)?;
+        writeln!(file, "# action = key, key, ...")?;
+        for action in &Action::ALL {
+            let names: Vec<String> = self
+                .keys(*action)
+                .iter()
+                .map(|key| key_name(*key))
+                .collect();
+            writeln!(file, "{} = {}", action.name(), names.join(", "))?;
+        }
+
+        Ok(())
+    }
+
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.keys.get(&action).map(|keys| &keys[..]).unwrap_or(&[])
+    }
+
+    pub fn actions(&self, key: KeyCode) -> Vec<Action> {
+        Action::ALL
+            .iter()
+            .cloned()
+            .filter(|action| self.keys(*action).contains(&key))
+            .collect()
+    }
+
+    /// Adds `key` to `action`, taking it away from any other action, unless
+    /// it is the last key left which opens the bindings menu.
+    pub fn bind(&mut self, action: Action, key: KeyCode) {
+        if action != Action::Bindings && *self.keys(Action::Bindings) == [key] {
+            return;
+        }
+        for keys in self.keys.values_mut() {
+            keys.retain(|bound| *bound != key);
+        }
+        self.keys.entry(action).or_insert_with(Vec::new).push(key);
+    }
+
+    /// Unbinds every key from `action`, except the most recently bound key
+    /// for opening the bindings menu, which is always kept.
+    pub fn clear(&mut self, action: Action) {
+        let kept = match action {
+            Action::Bindings => self.keys(action).last().cloned().into_iter().collect(),
+            _ => Vec::new(),
+        };
+        self.keys.insert(action, kept);
+    }
+}
+
+/// State of the screen for rebinding keys.
+pub struct BindingsMenu {
+    pub selected: usize,
+    /// Whether the next key pressed will be bound to the selected action.
+    pub capturing: bool,
+}
+
+impl BindingsMenu {
+    pub fn new() -> Self {
+        Self {
+            selected: 0,
+            capturing: false,
+        }
+    }
+
+    pub fn selected_action(&self) -> Action {
+        Action::ALL[self.selected]
+    }
+
+    /// Handles a key press while the menu is open. Returns false once the
+    /// menu should be closed.
+    pub fn key_down(&mut self, bindings: &mut Bindings, key: KeyCode) -> bool {
+        if self.capturing {
+            // Escape backs out rather than being bound
+            if key != KeyCode::Escape {
+                bindings.bind(self.selected_action(), key);
+            }
+            self.capturing = false;
+            return true;
+        }
+
+        // The menu's own keys are fixed so that it can't be locked out
+        match key {
+            KeyCode::Up => {
+                if self.selected > 0 {
+                    self.selected -= 1;
+                }
+            }
+            KeyCode::Down => {
+                if self.selected + 1 < Action::ALL.len() {
+                    self.selected += 1;
+                }
+            }
+            KeyCode::Return => {
+                self.capturing = true;
+            }
+            KeyCode::Back | KeyCode::Delete => {
+                bindings.clear(self.selected_action());
+            }
+            KeyCode::Escape => {
+                return false;
+            }
+            _ => {}
+        }
+
+        true
+    }
+}
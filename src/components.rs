@@ -11,6 +11,12 @@ pub struct SubTilePosition {
     pub pos: Point2<f32>,
 }
 
+/// Height above the pitch in pixels, for things in the air.
+#[derive(Component, Debug)]
+pub struct Elevation {
+    pub height: f32,
+}
+
 #[derive(Component, Debug)]
 pub struct Size {
     pub width: f32,
@@ -46,6 +52,10 @@ pub enum CursorState {
 #[derive(Component, Debug)]
 pub struct Cursor {
     pub state: CursorState,
+    /// Time left before the cursor may move again while a direction is held.
+    pub repeat_delay: f32,
+    /// Whether a direction was held when the cursor last came to rest.
+    pub held: bool,
 }
 
 pub enum PlayerState {
@@ -53,22 +63,114 @@ pub enum PlayerState {
     Running { path: Vec<Point2<u32>> },
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum PlayerTeam {
     Red,
     Blue,
 }
 
+impl PlayerTeam {
+    pub fn name(self) -> &'static str {
+        match self {
+            PlayerTeam::Red => "Red",
+            PlayerTeam::Blue => "Blue",
+        }
+    }
+
+    pub fn opponents(self) -> Self {
+        match self {
+            PlayerTeam::Red => PlayerTeam::Blue,
+            PlayerTeam::Blue => PlayerTeam::Red,
+        }
+    }
+}
+
 #[derive(Component)]
 pub struct Player {
     pub state: PlayerState,
     pub team: PlayerTeam,
 }
 
+/// A reserve waiting on the bench to come on. They have no position until
+/// they do.
+#[derive(Component, Debug, Default)]
+pub struct Bench;
+
+/// A player who has left the pitch for good, having been substituted or too
+/// badly hurt to play on. They have no position and take no further part in
+/// the match.
+#[derive(Component, Debug, Default)]
+pub struct OffPitch;
+
+/// A team's reserves in the order they are listed, which is the same on
+/// every client so that turn files can pick them out by position.
+pub fn sorted_bench(mut reserves: Vec<Entity>) -> Vec<Entity> {
+    reserves.sort_by_key(|reserve_id| reserve_id.id());
+    reserves
+}
+
+/// Where a player lines up at kick-off, as fractions of the way from their
+/// own goal line to halfway and from one touchline to the other.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct Formation {
+    pub depth: f32,
+    pub width: f32,
+}
+
+#[derive(Component, Clone, Copy, Debug, PartialEq)]
+pub enum Role {
+    Outfield,
+    /// Allowed to handle the ball inside their own penalty area.
+    Goalkeeper,
+}
+
+/// The best possible value of any attribute.
+pub const MAX_ATTRIBUTE: u32 = 10;
+
+/// A player's skills, each from 1 to `MAX_ATTRIBUTE`.
+#[derive(Component, Debug)]
+pub struct Attributes {
+    pub passing: u32,
+    pub shooting: u32,
+    /// Skill at bringing the ball under control.
+    pub control: u32,
+    /// Goalkeeping skill at stopping the ball and holding on to it.
+    pub handling: u32,
+}
+
+/// How many times a player has been booked. A second yellow card gets them
+/// sent off.
+#[derive(Component, Debug, Default)]
+pub struct Cards {
+    pub yellow: u32,
+}
+
+/// A player who has been hurt. A knock leaves them weaker for the rest of
+/// the match, while a serious injury means they can't play on.
+#[derive(Component, Debug)]
+pub struct Injury {
+    pub serious: bool,
+}
+
+/// What the steps of a path cost out of its distance.
+#[derive(Debug, Default)]
+pub struct StepCosts {
+    /// The cost of a diagonal step, if the path may take them.
+    pub diagonal: Option<f32>,
+    /// Tiles in opponents' zones of control, which cost `zone_cost` more to
+    /// step onto and end the path there if `zone_stops` is set.
+    pub zones: Vec<Point2<u32>>,
+    pub zone_cost: f32,
+    pub zone_stops: bool,
+    /// Tiles with players on, which a diagonal step can't squeeze between.
+    pub occupied: Vec<Point2<u32>>,
+}
+
 #[derive(Component)]
 pub struct CanMove {
     pub start: Point2<u32>,
     pub distance: u32,
+    pub costs: StepCosts,
     pub dests: Vec<Point2<u32>>,
     pub path: Vec<Point2<u32>>,
 }
@@ -82,10 +184,47 @@ pub enum BallState {
     Moving {
         player_id: Entity,
         path: Vec<Point2<u32>>,
+        /// How hard the ball was struck, from 0 up to 1 for the longest pass.
+        power: f32,
+        /// Struck at goal, so anyone it reaches blocks it rather than
+        /// receiving it.
+        shot: bool,
+    },
+    /// Loose and slowing down along a path worked out when it was set rolling.
+    Rolling {
+        /// Whoever touched it last.
+        player_id: Entity,
+        path: Vec<Point2<u32>>,
+        /// In tiles per second.
+        speed: f32,
     },
+    /// In the air on its way from `from` to `to`, where it will land.
+    Lofted {
+        player_id: Entity,
+        from: Point2<u32>,
+        to: Point2<u32>,
+        elapsed: f32,
+    },
+}
+
+impl BallState {
+    /// Whoever has the ball or played it last, unless it has stopped.
+    pub fn player_id(&self) -> Option<Entity> {
+        match *self {
+            BallState::Free => None,
+            BallState::Possessed { player_id }
+            | BallState::Moving { player_id, .. }
+            | BallState::Rolling { player_id, .. }
+            | BallState::Lofted { player_id, .. } => Some(player_id),
+        }
+    }
 }
 
 #[derive(Component)]
 pub struct Ball {
     pub state: BallState,
+    /// The team of whoever touched the ball last, remembered after it stops
+    /// so that the right side gets the restart if it has gone out of play.
+    /// Only the team is kept, as the player might since have been sent off.
+    pub last_touch: PlayerTeam,
 }
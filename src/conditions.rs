@@ -0,0 +1,148 @@
+//! The weather a match is played in, loaded with the match setup so that
+//! both players start from the same conditions.
+
+use std::path::Path;
+
+use nalgebra::Vector2;
+
+use config::{self, ConfigError, Entry};
+use rules::Rules;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Weather {
+    Dry,
+    /// The ball skids on, while players lose their footing.
+    Wet,
+    /// Everything is heavy going.
+    Snow,
+}
+
+impl Weather {
+    pub fn name(self) -> &'static str {
+        match self {
+            Weather::Dry => "Dry",
+            Weather::Wet => "Wet",
+            Weather::Snow => "Snow",
+        }
+    }
+}
+
+pub struct Conditions {
+    pub weather: Weather,
+    /// The way the wind blows across the pitch, one tile long, or nothing if
+    /// it is still.
+    pub wind: Vector2<i32>,
+}
+
+impl Default for Conditions {
+    fn default() -> Self {
+        Self {
+            weather: Weather::Dry,
+            wind: Vector2::new(0, 0),
+        }
+    }
+}
+
+fn parse_weather(entry: &Entry) -> Result<Weather, ConfigError> {
+    match entry.value {
+        "dry" => Ok(Weather::Dry),
+        "wet" => Ok(Weather::Wet),
+        "snow" => Ok(Weather::Snow),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+fn parse_wind(entry: &Entry) -> Result<Vector2<i32>, ConfigError> {
+    match entry.value {
+        "none" => Ok(Vector2::new(0, 0)),
+        "left" => Ok(Vector2::new(-1, 0)),
+        "right" => Ok(Vector2::new(1, 0)),
+        "up" => Ok(Vector2::new(0, -1)),
+        "down" => Ok(Vector2::new(0, 1)),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+/// `distance` made `by` tiles shorter, though never less than one tile.
+fn shorten(distance: u32, by: u32) -> u32 {
+    distance.saturating_sub(by).max(1)
+}
+
+impl Conditions {
+    /// Parses conditions, using the defaults for any which are not given.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let mut conditions = Self::default();
+
+        for entry in config::parse(contents)? {
+            match entry.name {
+                "weather" => conditions.weather = parse_weather(&entry)?,
+                "wind" => conditions.wind = parse_wind(&entry)?,
+                _ => return Err(ConfigError::unknown_field(&entry)),
+            }
+        }
+
+        Ok(conditions)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::parse(&config::read_file(path)?)
+    }
+
+    /// `rules` as played in these conditions.
+    pub fn apply(&self, mut rules: Rules) -> Rules {
+        match self.weather {
+            Weather::Dry => {}
+            Weather::Wet => {
+                rules.player_speed *= 0.9;
+                rules.pass_speed *= 1.2;
+                rules.shot_speed *= 1.1;
+                rules.roll_speed *= 1.2;
+                rules.roll_deceleration *= 0.8;
+                rules.dribble_move_distance = shorten(rules.dribble_move_distance, 1);
+                rules.ball_pass_distance += 1;
+            }
+            Weather::Snow => {
+                rules.player_speed *= 0.7;
+                rules.pass_speed *= 0.7;
+                rules.shot_speed *= 0.8;
+                rules.roll_speed *= 0.6;
+                rules.roll_deceleration *= 1.5;
+                rules.player_move_distance = shorten(rules.player_move_distance, 1);
+                rules.dribble_move_distance = shorten(rules.dribble_move_distance, 1);
+                rules.knock_on_distance = shorten(rules.knock_on_distance, 2);
+                rules.ball_pass_distance = shorten(rules.ball_pass_distance, 2);
+                rules.lofted_pass_distance = shorten(rules.lofted_pass_distance, 2);
+            }
+        }
+        rules
+    }
+
+    /// How far a pass heading along `direction` can go, with the wind
+    /// carrying it a tile further or holding it up a tile short.
+    pub fn pass_distance(&self, distance: u32, direction: Vector2<i32>) -> u32 {
+        let with_wind = self.wind.dot(&direction);
+        if with_wind > 0 {
+            distance + 1
+        } else if with_wind < 0 {
+            shorten(distance, 1)
+        } else {
+            distance
+        }
+    }
+
+    /// The longest a pass of `distance` can be in any direction.
+    pub fn longest_pass(&self, distance: u32) -> u32 {
+        self.pass_distance(distance, self.wind)
+    }
+
+    pub fn describe(&self) -> String {
+        let wind = match (self.wind.x, self.wind.y) {
+            (0, 0) => "no wind",
+            (x, _) if x < 0 => "wind blowing left",
+            (x, _) if x > 0 => "wind blowing right",
+            (_, y) if y < 0 => "wind blowing up",
+            _ => "wind blowing down",
+        };
+        format!("{}, {}", self.weather.name(), wind)
+    }
+}
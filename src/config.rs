@@ -0,0 +1,110 @@
+//! A minimal `name = value` configuration file format, used for input
+//! bindings and game rules.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read};
+use std::path::Path;
+
+pub struct Entry<'a> {
+    pub line: usize,
+    pub name: &'a str,
+    pub value: &'a str,
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Syntax {
+        line: usize,
+    },
+    UnknownField {
+        line: usize,
+        name: String,
+    },
+    InvalidValue {
+        line: usize,
+        name: String,
+        value: String,
+    },
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ConfigError::Io(ref err) => write!(f, "{}", err),
+            ConfigError::Syntax { line } => write!(f, "line {}: expected `name = value`", line),
+            ConfigError::UnknownField { line, ref name } => {
+                write!(f, "line {}: unknown field `{}`", line, name)
+            }
+            ConfigError::InvalidValue {
+                line,
+                ref name,
+                ref value,
+            } => write!(f, "line {}: invalid value `{}` for `{}`", line, value, name),
+        }
+    }
+}
+
+impl From<io::Error> for ConfigError {
+    fn from(err: io::Error) -> Self {
+        ConfigError::Io(err)
+    }
+}
+
+impl ConfigError {
+    pub fn invalid_value(entry: &Entry) -> Self {
+        ConfigError::InvalidValue {
+            line: entry.line,
+            name: entry.name.to_string(),
+            value: entry.value.to_string(),
+        }
+    }
+
+    pub fn unknown_field(entry: &Entry) -> Self {
+        ConfigError::UnknownField {
+            line: entry.line,
+            name: entry.name.to_string(),
+        }
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<String, ConfigError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+    Ok(contents)
+}
+
+/// Splits `contents` into entries, skipping blank lines and `#` comments.
+pub fn parse(contents: &str) -> Result<Vec<Entry>, ConfigError> {
+    let mut entries = Vec::new();
+
+    for (i, line) in contents.lines().enumerate() {
+        let line_number = i + 1;
+        let line = match line.find('#') {
+            Some(comment) => &line[..comment],
+            None => line,
+        }
+        .trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let equals = line
+            .find('=')
+            .ok_or(ConfigError::Syntax { line: line_number })?;
+        let name = line[..equals].trim();
+        let value = line[equals + 1..].trim();
+        if name.is_empty() {
+            return Err(ConfigError::Syntax { line: line_number });
+        }
+
+        entries.push(Entry {
+            line: line_number,
+            name,
+            value,
+        });
+    }
+
+    Ok(entries)
+}
@@ -1,92 +1,188 @@
 use std::path::Path;
 
+use ggez::input::gamepad::GamepadId;
 use ggez::{event, graphics, timer, Context, GameResult};
-use nalgebra::Point2;
+use nalgebra::{Point2, Vector2};
 use specs::{RunNow, World};
 use tiled;
 
+use bindings::{Bindings, BindingsMenu};
 use components::{
-    Ball, BallState, CanMove, Cursor, CursorState, Player, PlayerState, PlayerTeam, Size, Sprite,
-    SubTilePosition, TilePosition,
+    Attributes, Ball, BallState, Bench, CanMove, Cards, Cursor, CursorState, Elevation, Formation,
+    Injury, OffPitch, Player, PlayerState, PlayerTeam, Role, Size, Sprite, SubTilePosition,
+    TilePosition,
 };
-use render::RenderSystem;
-use resources::{Assets, Camera, DeltaTime, Input, Map, Turn, TurnState};
+use conditions::Conditions;
+use render::{self, RenderSystem};
+use resources::{
+    Action, ActionMenu, Assets, Camera, Challenges, DeltaTime, Input, Map, MatchClock, MatchEvents,
+    Notice, PendingRestart, Rng, Score, Shootout, Steps, Substitutions, Turn, TurnLog, TurnState,
+};
+use rules::{Rules, RulesWatcher};
 use systems::{
-    ActionMenuSystem, BallDribbleSystem, BallMovementSystem, CameraSystem, CursorMovementSystem,
-    PassSelectSystem, PathSelectSystem, PlayerMovementSystem, PlayerSelectSystem, RunSelectSystem,
+    ActionMenuSystem, ActivationEndSystem, BallDribbleSystem, BallMovementSystem, CameraSystem,
+    CollisionSystem, CursorMovementSystem, InjurySystem, KickOffSystem, KnockOnSelectSystem,
+    LoftedPassSelectSystem, MouseSystem, PassSelectSystem, PathSelectSystem, PlayerMovementSystem,
+    PlayerSelectSystem, ReplaySystem, RestartSystem, RunSelectSystem, ShootoutSelectSystem,
+    ShootoutSystem, ShotSelectSystem, SubstituteSelectSystem, SubstitutionSystem,
+    ThroughBallSelectSystem,
 };
+use turn_file;
+
+const TURN_FILE: &str = "turn.tbf";
+const RULES_FILE: &str = "resources/rules.cfg";
+const MATCH_FILE: &str = "resources/match.cfg";
+// Both players of a match must start from the same seed
+const MATCH_SEED: u64 = 0x7462_6621;
+
+/// Where an entity on `pos` is drawn, and how big it is: one tile of the map.
+fn placement(world: &World, pos: Point2<u32>) -> (Point2<f32>, Size) {
+    let map = world.read_resource::<Map>();
+    (map.tile_to_world(pos), tile_size(&map))
+}
+
+fn tile_size(map: &Map) -> Size {
+    let tile_size = map.tile_size();
+    Size {
+        width: tile_size.x,
+        height: tile_size.y,
+    }
+}
 
 fn create_cursor(world: &mut World, pos: Point2<u32>) {
+    let (world_pos, size) = placement(world, pos);
     world
         .create_entity()
         .with(Cursor {
             state: CursorState::Still,
+            repeat_delay: 0.0,
+            held: false,
         })
         .with(TilePosition { pos })
-        .with(SubTilePosition {
-            pos: Point2::new((pos.x * 64) as f32, (pos.y * 64) as f32),
-        })
-        .with(Size {
-            width: 64.0,
-            height: 64.0,
-        })
+        .with(SubTilePosition { pos: world_pos })
+        .with(size)
         .with(Sprite { image_id: "cursor" })
         .build();
 }
 
-fn create_player(world: &mut World, pos: Point2<u32>, team: PlayerTeam) {
+fn create_player(
+    world: &mut World,
+    team: PlayerTeam,
+    role: Role,
+    formation: Formation,
+    attributes: Attributes,
+) {
+    let pos = world.read_resource::<Map>().formation_tile(team, formation);
+    let (world_pos, size) = placement(world, pos);
     world
         .create_entity()
         .with(Player {
             state: PlayerState::Still,
             team,
         })
+        .with(role)
+        .with(formation)
+        .with(attributes)
+        .with(Cards::default())
         .with(TilePosition { pos })
-        .with(SubTilePosition {
-            pos: Point2::new((pos.x * 64) as f32, (pos.y * 64) as f32),
-        })
-        .with(Size {
-            width: 64.0,
-            height: 64.0,
-        })
-        .with(Sprite {
-            image_id: match team {
-                PlayerTeam::Red => "player-red",
-                PlayerTeam::Blue => "player-blue",
-            },
+        .with(SubTilePosition { pos: world_pos })
+        .with(size)
+        .with(player_sprite(team, role))
+        .build();
+}
+
+/// Puts a player on `team`'s bench, off the pitch until they are brought on.
+fn create_reserve(world: &mut World, team: PlayerTeam, role: Role, attributes: Attributes) {
+    let size = tile_size(&world.read_resource::<Map>());
+    world
+        .create_entity()
+        .with(Player {
+            state: PlayerState::Still,
+            team,
         })
+        .with(role)
+        .with(attributes)
+        .with(Cards::default())
+        .with(Bench)
+        .with(size)
+        .with(player_sprite(team, role))
         .build();
 }
 
-fn create_ball(world: &mut World, pos: Point2<u32>) {
+fn player_sprite(team: PlayerTeam, role: Role) -> Sprite {
+    Sprite {
+        image_id: match (team, role) {
+            (PlayerTeam::Red, Role::Outfield) => "player-red",
+            (PlayerTeam::Blue, Role::Outfield) => "player-blue",
+            (PlayerTeam::Red, Role::Goalkeeper) => "keeper-red",
+            (PlayerTeam::Blue, Role::Goalkeeper) => "keeper-blue",
+        },
+    }
+}
+
+fn create_ball(world: &mut World, pos: Point2<u32>, kick_off_team: PlayerTeam) {
+    let (world_pos, size) = placement(world, pos);
     world
         .create_entity()
         .with(Ball {
             state: BallState::Free,
+            last_touch: kick_off_team,
         })
         .with(TilePosition { pos })
-        .with(SubTilePosition {
-            pos: Point2::new((pos.x * 64) as f32, (pos.y * 64) as f32),
-        })
-        .with(Size {
-            width: 64.0,
-            height: 64.0,
-        })
+        .with(SubTilePosition { pos: world_pos })
+        .with(size)
+        .with(Elevation { height: 0.0 })
         .with(Sprite { image_id: "ball" })
         .build();
 }
 
+fn gamepad_action(button: event::Button) -> Option<Action> {
+    match button {
+        event::Button::DPadUp => Some(Action::CursorUp),
+        event::Button::DPadDown => Some(Action::CursorDown),
+        event::Button::DPadLeft => Some(Action::CursorLeft),
+        event::Button::DPadRight => Some(Action::CursorRight),
+        event::Button::South => Some(Action::Select),
+        event::Button::East => Some(Action::Cancel),
+        // The shoulders pan the camera as well as the right stick
+        event::Button::LeftTrigger => Some(Action::CameraLeft),
+        event::Button::RightTrigger => Some(Action::CameraRight),
+        event::Button::LeftTrigger2 => Some(Action::CameraUp),
+        event::Button::RightTrigger2 => Some(Action::CameraDown),
+        event::Button::Start => Some(Action::EndTurn),
+        _ => None,
+    }
+}
+
 pub struct Game {
     world: World,
     camera_system: CameraSystem,
+    mouse_system: MouseSystem,
     cursor_movement_system: CursorMovementSystem,
     player_select_system: PlayerSelectSystem,
     run_select_system: RunSelectSystem,
     pass_select_system: PassSelectSystem,
+    lofted_pass_select_system: LoftedPassSelectSystem,
+    through_ball_select_system: ThroughBallSelectSystem,
+    knock_on_select_system: KnockOnSelectSystem,
+    shot_select_system: ShotSelectSystem,
     path_select_system: PathSelectSystem,
     player_movement_system: PlayerMovementSystem,
     ball_dribble_system: BallDribbleSystem,
+    collision_system: CollisionSystem,
     ball_movement_system: BallMovementSystem,
+    injury_system: InjurySystem,
+    activation_end_system: ActivationEndSystem,
+    kick_off_system: KickOffSystem,
+    restart_system: RestartSystem,
+    substitute_select_system: SubstituteSelectSystem,
+    substitution_system: SubstitutionSystem,
+    shootout_select_system: ShootoutSelectSystem,
+    shootout_system: ShootoutSystem,
+    replay_system: ReplaySystem,
+    bindings: Bindings,
+    bindings_menu: Option<BindingsMenu>,
+    rules_watcher: RulesWatcher,
 }
 
 impl Game {
@@ -106,6 +202,16 @@ impl Game {
         assets
             .images
             .insert("player-blue".to_string(), player_blue_image);
+        let mut keeper_red_image = graphics::Image::new(ctx, "/keeper-red.png").unwrap();
+        keeper_red_image.set_filter(graphics::FilterMode::Nearest);
+        assets
+            .images
+            .insert("keeper-red".to_string(), keeper_red_image);
+        let mut keeper_blue_image = graphics::Image::new(ctx, "/keeper-blue.png").unwrap();
+        keeper_blue_image.set_filter(graphics::FilterMode::Nearest);
+        assets
+            .images
+            .insert("keeper-blue".to_string(), keeper_blue_image);
         let mut highlight_image = graphics::Image::new(ctx, "/highlight.png").unwrap();
         highlight_image.set_filter(graphics::FilterMode::Nearest);
         assets
@@ -117,6 +223,35 @@ impl Game {
         let mut ball_image = graphics::Image::new(ctx, "/ball.png").unwrap();
         ball_image.set_filter(graphics::FilterMode::Nearest);
         assets.images.insert("ball".to_string(), ball_image);
+        // A single white pixel, stretched and coloured into lines and
+        // blocks
+        let white_image = graphics::Image::from_rgba8(ctx, 1, 1, &[255, 255, 255, 255])?;
+        assets.images.insert("white".to_string(), white_image);
+        let shadow_mesh = graphics::Mesh::new_circle(
+            ctx,
+            graphics::DrawMode::fill(),
+            Point2::new(0.0, 0.0),
+            1.0,
+            0.01,
+            graphics::WHITE,
+        )?;
+        assets.meshes.insert("shadow".to_string(), shadow_mesh);
+
+        let mut notice = Notice::default();
+
+        let bindings_path = Bindings::path(ctx);
+        let bindings = if bindings_path.exists() {
+            Bindings::load(&bindings_path).unwrap_or_else(|err| {
+                notice.show(format!(
+                    "Failed to load {}: {}",
+                    bindings_path.display(),
+                    err
+                ));
+                Bindings::default()
+            })
+        } else {
+            Bindings::default()
+        };
 
         // Load map
         let map =
@@ -129,62 +264,374 @@ impl Game {
         }
 
         let mut world = World::new();
+        world.register::<Attributes>();
+        world.register::<Cards>();
+        world.register::<Injury>();
+        world.register::<Bench>();
+        world.register::<OffPitch>();
+        world.register::<Formation>();
         world.register::<Ball>();
         world.register::<CanMove>();
         world.register::<Player>();
+        world.register::<Role>();
         world.register::<TilePosition>();
         world.register::<SubTilePosition>();
         world.register::<Size>();
         world.register::<Sprite>();
         world.register::<Cursor>();
+        world.register::<Elevation>();
 
         world.add_resource(assets);
-        world.add_resource(Camera::new());
-        world.add_resource(Map { map });
+        let map = Map { map };
+        let screen = graphics::screen_coordinates(ctx);
+        world.add_resource(Camera::new(
+            Vector2::new(screen.w, screen.h),
+            map.pixel_size(),
+        ));
+        world.add_resource(map);
         world.add_resource(DeltaTime { dt: 0.0 });
+        // Both players need the same match setup, as they need the same
+        // rules. A broken match or rules file leaves the defaults in play
+        // rather than stopping the game from starting
+        let conditions = Conditions::load(Path::new(MATCH_FILE)).unwrap_or_else(|err| {
+            notice.show(format!("Failed to load {}: {}", MATCH_FILE, err));
+            Conditions::default()
+        });
+        let rules = Rules::load(Path::new(RULES_FILE)).unwrap_or_else(|err| {
+            notice.show(format!("Failed to load {}: {}", RULES_FILE, err));
+            Rules::default()
+        });
+        world.add_resource(conditions.apply(rules));
+        world.add_resource(conditions);
         world.add_resource(Input::default());
+        world.add_resource(ActionMenu::new());
+        world.add_resource(Rng::new(MATCH_SEED));
+        world.add_resource(Score::default());
+        world.add_resource(MatchEvents::default());
+        world.add_resource(PendingRestart::default());
+        world.add_resource(Substitutions::default());
+        world.add_resource(Challenges::default());
+        world.add_resource(Steps::default());
+
+        world.add_resource(MatchClock::new());
+        world.add_resource(Shootout::default());
+        world.add_resource(notice);
 
+        let kick_off_team = world.read_resource::<MatchClock>().kick_off_team();
         world.add_resource(Turn {
-            state: TurnState::SelectPlayer,
+            state: TurnState::KickOff {
+                team: kick_off_team,
+            },
+            side: PlayerTeam::Red,
         });
 
         create_cursor(&mut world, Point2::new(0, 0));
-        create_player(&mut world, Point2::new(2, 2), PlayerTeam::Red);
-        create_player(&mut world, Point2::new(4, 4), PlayerTeam::Red);
-        create_player(&mut world, Point2::new(2, 6), PlayerTeam::Red);
-        create_player(&mut world, Point2::new(10, 2), PlayerTeam::Blue);
-        create_player(&mut world, Point2::new(12, 6), PlayerTeam::Blue);
-        create_player(&mut world, Point2::new(11, 8), PlayerTeam::Blue);
+        create_player(
+            &mut world,
+            PlayerTeam::Red,
+            Role::Outfield,
+            Formation {
+                depth: 0.9,
+                width: 0.35,
+            },
+            Attributes {
+                passing: 7,
+                shooting: 5,
+                control: 6,
+                handling: 2,
+            },
+        );
+        create_player(
+            &mut world,
+            PlayerTeam::Red,
+            Role::Outfield,
+            Formation {
+                depth: 0.9,
+                width: 0.65,
+            },
+            Attributes {
+                passing: 5,
+                shooting: 4,
+                control: 7,
+                handling: 1,
+            },
+        );
+        create_player(
+            &mut world,
+            PlayerTeam::Red,
+            Role::Outfield,
+            Formation {
+                depth: 0.4,
+                width: 0.5,
+            },
+            Attributes {
+                passing: 4,
+                shooting: 6,
+                control: 4,
+                handling: 3,
+            },
+        );
+        create_player(
+            &mut world,
+            PlayerTeam::Blue,
+            Role::Outfield,
+            Formation {
+                depth: 0.9,
+                width: 0.35,
+            },
+            Attributes {
+                passing: 6,
+                shooting: 4,
+                control: 5,
+                handling: 2,
+            },
+        );
+        create_player(
+            &mut world,
+            PlayerTeam::Blue,
+            Role::Outfield,
+            Formation {
+                depth: 0.9,
+                width: 0.65,
+            },
+            Attributes {
+                passing: 8,
+                shooting: 7,
+                control: 8,
+                handling: 1,
+            },
+        );
+        create_player(
+            &mut world,
+            PlayerTeam::Blue,
+            Role::Outfield,
+            Formation {
+                depth: 0.4,
+                width: 0.5,
+            },
+            Attributes {
+                passing: 3,
+                shooting: 3,
+                control: 5,
+                handling: 2,
+            },
+        );
+
+        create_player(
+            &mut world,
+            PlayerTeam::Red,
+            Role::Goalkeeper,
+            Formation {
+                depth: 0.0,
+                width: 0.5,
+            },
+            Attributes {
+                passing: 4,
+                shooting: 2,
+                control: 5,
+                handling: 7,
+            },
+        );
+        create_player(
+            &mut world,
+            PlayerTeam::Blue,
+            Role::Goalkeeper,
+            Formation {
+                depth: 0.0,
+                width: 0.5,
+            },
+            Attributes {
+                passing: 5,
+                shooting: 1,
+                control: 4,
+                handling: 8,
+            },
+        );
 
-        create_ball(&mut world, Point2::new(2, 4));
+        create_reserve(
+            &mut world,
+            PlayerTeam::Red,
+            Role::Outfield,
+            Attributes {
+                passing: 5,
+                shooting: 6,
+                control: 5,
+                handling: 1,
+            },
+        );
+        create_reserve(
+            &mut world,
+            PlayerTeam::Red,
+            Role::Goalkeeper,
+            Attributes {
+                passing: 3,
+                shooting: 1,
+                control: 4,
+                handling: 6,
+            },
+        );
+        create_reserve(
+            &mut world,
+            PlayerTeam::Blue,
+            Role::Outfield,
+            Attributes {
+                passing: 6,
+                shooting: 4,
+                control: 6,
+                handling: 2,
+            },
+        );
+        create_reserve(
+            &mut world,
+            PlayerTeam::Blue,
+            Role::Goalkeeper,
+            Attributes {
+                passing: 4,
+                shooting: 2,
+                control: 3,
+                handling: 7,
+            },
+        );
+
+        let centre_spot = world.read_resource::<Map>().centre_spot();
+        create_ball(&mut world, centre_spot, kick_off_team);
+
+        // Line up for kick-off before the first turn starts
+        let mut kick_off_system = KickOffSystem;
+        kick_off_system.run_now(&world.res);
+        let mut restart_system = RestartSystem;
+        restart_system.run_now(&world.res);
+
+        let start_hash = turn_file::state_hash(&world);
+        world.add_resource(TurnLog::new(start_hash));
 
         Ok(Self {
             world,
             camera_system: CameraSystem,
+            mouse_system: MouseSystem,
             cursor_movement_system: CursorMovementSystem,
             player_select_system: PlayerSelectSystem,
             run_select_system: RunSelectSystem,
             pass_select_system: PassSelectSystem,
+            lofted_pass_select_system: LoftedPassSelectSystem,
+            through_ball_select_system: ThroughBallSelectSystem,
+            knock_on_select_system: KnockOnSelectSystem,
+            shot_select_system: ShotSelectSystem,
             path_select_system: PathSelectSystem,
             player_movement_system: PlayerMovementSystem,
             ball_dribble_system: BallDribbleSystem,
+            collision_system: CollisionSystem,
             ball_movement_system: BallMovementSystem,
+            injury_system: InjurySystem,
+            activation_end_system: ActivationEndSystem,
+            kick_off_system: KickOffSystem,
+            restart_system: RestartSystem,
+            substitute_select_system: SubstituteSelectSystem,
+            substitution_system: SubstitutionSystem,
+            shootout_select_system: ShootoutSelectSystem,
+            shootout_system: ShootoutSystem,
+            replay_system: ReplaySystem,
+            bindings,
+            bindings_menu: None,
+            rules_watcher: RulesWatcher::new(Path::new(RULES_FILE)),
         })
     }
+
+    fn update_turn_file(&mut self) {
+        let (export_turn, import_turn) = {
+            let input = self.world.read_resource::<Input>();
+            (
+                input.was_pressed(Action::EndTurn),
+                input.was_pressed(Action::LoadTurn),
+            )
+        };
+        let (idle, replay_finished) = {
+            let turn = self.world.read_resource::<Turn>();
+            let log = self.world.read_resource::<TurnLog>();
+            let idle = match turn.state {
+                TurnState::SelectPlayer
+                | TurnState::ShootoutDive { .. }
+                | TurnState::ShootoutTaker { .. }
+                | TurnState::ShootoutReveal { .. } => true,
+                _ => false,
+            };
+            (idle, idle && log.replaying && log.replay.is_empty())
+        };
+
+        if replay_finished {
+            // The opponent's turn has been played out, so ours starts now.
+            let side = self.world.read_resource::<Turn>().side;
+            self.world.write_resource::<Turn>().side = side.opponents();
+            let hash = turn_file::state_hash(&self.world);
+            let mut log = self.world.write_resource::<TurnLog>();
+            log.replaying = false;
+            log.reset(hash);
+        }
+
+        if !idle || self.world.read_resource::<TurnLog>().replaying {
+            return;
+        }
+
+        let result = if export_turn {
+            turn_file::export_turn(&mut self.world, Path::new(TURN_FILE))
+                .map_err(|err| format!("Failed to export turn: {}", err))
+        } else if import_turn {
+            turn_file::import_turn(&mut self.world, Path::new(TURN_FILE))
+                .map_err(|err| format!("Failed to import turn: {}", err))
+        } else {
+            Ok(())
+        };
+        if let Err(message) = result {
+            self.world.write_resource::<Notice>().show(message);
+        }
+    }
 }
 
 impl event::EventHandler for Game {
     fn update(&mut self, ctx: &mut Context) -> GameResult<()> {
         let dt = timer::duration_to_f64(timer::delta(ctx));
         self.world.write_resource::<DeltaTime>().dt = dt as f32;
+        self.world.write_resource::<Notice>().update(dt as f32);
+
+        // Let designers tune the rules while the game is running
+        if cfg!(debug_assertions) {
+            match self.rules_watcher.poll(dt as f32) {
+                Some(Ok(rules)) => {
+                    let rules = self.world.read_resource::<Conditions>().apply(rules);
+                    *self.world.write_resource::<Rules>() = rules;
+                    self.world
+                        .write_resource::<Notice>()
+                        .show(format!("Reloaded {}", RULES_FILE));
+                }
+                Some(Err(err)) => {
+                    self.world
+                        .write_resource::<Notice>()
+                        .show(format!("Failed to reload {}: {}", RULES_FILE, err));
+                }
+                None => {}
+            }
+        }
+
+        // The game is paused while keys are being rebound
+        if self.bindings_menu.is_some() {
+            self.world.write_resource::<Input>().end_frame();
+            return Ok(());
+        }
 
         self.camera_system.run_now(&self.world.res);
+        if !self.world.read_resource::<TurnLog>().replaying {
+            self.mouse_system.run_now(&self.world.res);
+        }
 
         let state = self.world.read_resource::<Turn>().state.clone();
         match state {
             TurnState::SelectPlayer => {
-                self.cursor_movement_system.run_now(&self.world.res);
-                self.player_select_system.run_now(&self.world.res);
+                if self.world.read_resource::<TurnLog>().replaying {
+                    self.replay_system.run_now(&self.world.res);
+                } else {
+                    self.cursor_movement_system.run_now(&self.world.res);
+                    self.player_select_system.run_now(&self.world.res);
+                }
             }
             TurnState::ActionMenu { .. } => {
                 let mut action_menu_system = ActionMenuSystem;
@@ -196,23 +643,94 @@ impl event::EventHandler for Game {
                 self.run_select_system.run_now(&self.world.res);
             }
             TurnState::Running { .. } => {
+                // The ball only moves by itself once tackled away
                 self.player_movement_system.run_now(&self.world.res);
+                self.ball_movement_system.run_now(&self.world.res);
                 self.ball_dribble_system.run_now(&self.world.res);
+                self.collision_system.run_now(&self.world.res);
+                self.injury_system.run_now(&self.world.res);
+                self.activation_end_system.run_now(&self.world.res);
             }
             TurnState::SelectPass { .. } => {
                 self.cursor_movement_system.run_now(&self.world.res);
                 self.path_select_system.run_now(&self.world.res);
                 self.pass_select_system.run_now(&self.world.res);
             }
+            TurnState::SelectLoftedPass { .. } => {
+                self.cursor_movement_system.run_now(&self.world.res);
+                self.lofted_pass_select_system.run_now(&self.world.res);
+            }
             TurnState::Passing { .. } => {
                 self.ball_movement_system.run_now(&self.world.res);
+                self.injury_system.run_now(&self.world.res);
+                self.activation_end_system.run_now(&self.world.res);
+            }
+            TurnState::SelectThroughBall { .. } => {
+                self.cursor_movement_system.run_now(&self.world.res);
+                self.path_select_system.run_now(&self.world.res);
+                self.through_ball_select_system.run_now(&self.world.res);
+            }
+            TurnState::SelectReceiver { .. } => {
+                self.cursor_movement_system.run_now(&self.world.res);
+                self.through_ball_select_system.run_now(&self.world.res);
+            }
+            TurnState::SelectKnockOn { .. } => {
+                self.cursor_movement_system.run_now(&self.world.res);
+                self.path_select_system.run_now(&self.world.res);
+                self.knock_on_select_system.run_now(&self.world.res);
+            }
+            TurnState::SelectShot { .. } => {
+                self.cursor_movement_system.run_now(&self.world.res);
+                self.shot_select_system.run_now(&self.world.res);
+            }
+            TurnState::ThroughBall { .. } | TurnState::KnockOn { .. } => {
+                // The ball and the runners onto it all move at once
+                self.player_movement_system.run_now(&self.world.res);
+                self.ball_movement_system.run_now(&self.world.res);
+                self.ball_dribble_system.run_now(&self.world.res);
+                self.collision_system.run_now(&self.world.res);
+                self.injury_system.run_now(&self.world.res);
+                self.activation_end_system.run_now(&self.world.res);
+            }
+            TurnState::Restart { .. } => {
+                self.restart_system.run_now(&self.world.res);
+            }
+            TurnState::KickOff { .. } => {
+                self.kick_off_system.run_now(&self.world.res);
+            }
+            TurnState::SelectSubstitute { .. } => {
+                self.substitute_select_system.run_now(&self.world.res);
             }
+            TurnState::Substitution { .. } => {
+                self.substitution_system.run_now(&self.world.res);
+            }
+            TurnState::ShootoutDive { .. }
+            | TurnState::ShootoutTaker { .. }
+            | TurnState::ShootoutReveal { .. } => {
+                if self.world.read_resource::<TurnLog>().replaying {
+                    self.replay_system.run_now(&self.world.res);
+                } else {
+                    self.cursor_movement_system.run_now(&self.world.res);
+                    self.shootout_select_system.run_now(&self.world.res);
+                }
+            }
+            TurnState::ShootoutShot { .. } => {
+                self.cursor_movement_system.run_now(&self.world.res);
+                self.shootout_select_system.run_now(&self.world.res);
+            }
+            TurnState::Shootout | TurnState::PenaltyKick { .. } => {
+                self.shootout_system.run_now(&self.world.res);
+            }
+            TurnState::FullTime => {}
         }
 
+        // Clear away anyone sent off
+        self.world.maintain();
+
+        self.update_turn_file();
+
         // Reset input states which must be pressed each time rather than held
-        let mut input = self.world.write_resource::<Input>();
-        input.select = false;
-        input.cancel = false;
+        self.world.write_resource::<Input>().end_frame();
 
         Ok(())
     }
@@ -241,51 +759,71 @@ impl event::EventHandler for Game {
             (Point2::new(8.0, 800.0 - height - 8.0), graphics::WHITE),
         )?;
 
+        // Display any notice just above it
+        if let Some(ref notice) = self.world.read_resource::<Notice>().text {
+            let mut notice_text = graphics::Text::new(notice.as_str());
+            notice_text.set_font(graphics::Font::default(), graphics::Scale::uniform(24.0));
+            let notice_height = notice_text.height(ctx) as f32;
+            graphics::draw(
+                ctx,
+                &notice_text,
+                (
+                    Point2::new(8.0, 800.0 - height - notice_height - 16.0),
+                    graphics::WHITE,
+                ),
+            )?;
+        }
+
+        if let Some(ref bindings_menu) = self.bindings_menu {
+            let assets = self.world.read_resource::<Assets>();
+            render::draw_bindings_menu(
+                ctx,
+                &assets.images["white"],
+                &self.bindings,
+                bindings_menu,
+            )?;
+        }
+
         graphics::present(ctx)?;
         Ok(())
     }
 
     fn key_down_event(
         &mut self,
-        _ctx: &mut Context,
+        ctx: &mut Context,
         keycode: event::KeyCode,
         _keymod: event::KeyMods,
         _repeat: bool,
     ) {
-        let mut input = self.world.write_resource::<Input>();
-
-        match keycode {
-            event::KeyCode::Left => {
-                input.left = true;
-            }
-            event::KeyCode::Up => {
-                input.up = true;
-            }
-            event::KeyCode::Down => {
-                input.down = true;
-            }
-            event::KeyCode::Right => {
-                input.right = true;
-            }
-            event::KeyCode::Return | event::KeyCode::Space => {
-                input.select = true;
-            }
-            event::KeyCode::Escape => {
-                input.cancel = true;
-            }
-            event::KeyCode::W => {
-                input.w = true;
-            }
-            event::KeyCode::A => {
-                input.a = true;
+        if let Some(mut bindings_menu) = self.bindings_menu.take() {
+            if bindings_menu.key_down(&mut self.bindings, keycode) {
+                self.bindings_menu = Some(bindings_menu);
+            } else if let Err(err) = self.bindings.save(&Bindings::path(ctx)) {
+                self.world
+                    .write_resource::<Notice>()
+                    .show(format!("Failed to save key bindings: {}", err));
             }
-            event::KeyCode::S => {
-                input.s = true;
-            }
-            event::KeyCode::D => {
-                input.d = true;
+            return;
+        }
+
+        let actions = self.bindings.actions(keycode);
+        if actions.contains(&Action::Bindings) {
+            self.bindings_menu = Some(BindingsMenu::new());
+            return;
+        }
+
+        let mut input = self.world.write_resource::<Input>();
+        for action in actions {
+            match action {
+                Action::CursorUp
+                | Action::CursorDown
+                | Action::CursorLeft
+                | Action::CursorRight => {
+                    input.mouse_active = false;
+                }
+                _ => {}
             }
-            _ => {}
+            input.press(action);
         }
     }
 
@@ -296,39 +834,119 @@ impl event::EventHandler for Game {
         _keymod: event::KeyMods,
     ) {
         let mut input = self.world.write_resource::<Input>();
+        for action in self.bindings.actions(keycode) {
+            input.release(action);
+        }
+    }
 
-        match keycode {
-            event::KeyCode::Left => {
-                input.left = false;
-            }
-            event::KeyCode::Up => {
-                input.up = false;
-            }
-            event::KeyCode::Down => {
-                input.down = false;
-            }
-            event::KeyCode::Right => {
-                input.right = false;
-            }
-            event::KeyCode::Return | event::KeyCode::Space => {
-                input.select = false;
+    fn mouse_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        let mut input = self.world.write_resource::<Input>();
+        input.mouse_pos = Point2::new(x, y);
+        input.mouse_active = true;
+
+        if button == event::MouseButton::Left {
+            input.mouse_dragging = true;
+        }
+    }
+
+    fn mouse_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::MouseButton,
+        x: f32,
+        y: f32,
+    ) {
+        let mut input = self.world.write_resource::<Input>();
+        input.mouse_pos = Point2::new(x, y);
+        input.mouse_active = true;
+
+        match button {
+            event::MouseButton::Left => {
+                // Letting go at the end of a drag picks where it ended
+                input.mouse_dragging = false;
+                input.mouse_clicked = true;
             }
-            event::KeyCode::Escape => {
-                input.cancel = false;
+            event::MouseButton::Right => {
+                input.trigger(Action::Cancel);
             }
-            event::KeyCode::W => {
-                input.w = false;
+            _ => {}
+        }
+    }
+
+    fn mouse_wheel_event(&mut self, _ctx: &mut Context, _x: f32, y: f32) {
+        self.world.write_resource::<Input>().wheel += y;
+    }
+
+    fn mouse_motion_event(&mut self, _ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        let mut input = self.world.write_resource::<Input>();
+        input.mouse_pos = Point2::new(x, y);
+        input.mouse_active = true;
+    }
+
+    fn gamepad_button_down_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::Button,
+        _id: GamepadId,
+    ) {
+        if let Some(action) = gamepad_action(button) {
+            let mut input = self.world.write_resource::<Input>();
+            input.mouse_active = false;
+            input.press(action);
+        }
+    }
+
+    fn gamepad_button_up_event(
+        &mut self,
+        _ctx: &mut Context,
+        button: event::Button,
+        _id: GamepadId,
+    ) {
+        if let Some(action) = gamepad_action(button) {
+            self.world.write_resource::<Input>().release(action);
+        }
+    }
+
+    fn gamepad_axis_event(
+        &mut self,
+        _ctx: &mut Context,
+        axis: event::Axis,
+        value: f32,
+        _id: GamepadId,
+    ) {
+        let mut input = self.world.write_resource::<Input>();
+
+        let was_centred = input.stick.norm() < 0.5;
+        match axis {
+            event::Axis::LeftStickX => {
+                input.stick.x = value;
             }
-            event::KeyCode::A => {
-                input.a = false;
+            event::Axis::LeftStickY => {
+                input.stick.y = value;
             }
-            event::KeyCode::S => {
-                input.s = false;
+            event::Axis::RightStickX => {
+                input.camera_stick.x = value;
             }
-            event::KeyCode::D => {
-                input.d = false;
+            event::Axis::RightStickY => {
+                input.camera_stick.y = value;
             }
             _ => {}
         }
+
+        // Flicking the stick navigates menus
+        if was_centred && input.stick.norm() >= 0.5 {
+            input.mouse_active = false;
+            if input.stick.y > input.stick.x.abs() {
+                input.trigger(Action::CursorUp);
+            } else if -input.stick.y > input.stick.x.abs() {
+                input.trigger(Action::CursorDown);
+            }
+        }
     }
 }
@@ -5,11 +5,16 @@ extern crate specs;
 extern crate specs_derive;
 extern crate tiled;
 
+mod bindings;
 mod components;
+mod conditions;
+mod config;
 mod game;
 mod render;
 mod resources;
+mod rules;
 mod systems;
+mod turn_file;
 
 use std::env;
 use std::path::PathBuf;
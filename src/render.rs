@@ -1,22 +1,34 @@
+use std::cmp::Ordering;
+use std::f32::consts::PI;
+
 use ggez::graphics::spritebatch::SpriteBatch;
-use ggez::{graphics, Context};
-use nalgebra::Point2;
-use specs::{Fetch, Join, ReadStorage, System};
+use ggez::{graphics, timer, Context, GameResult};
+use nalgebra::{Matrix4, Point2, Vector2};
+use specs::{Entities, Fetch, Join, ReadStorage, System};
 
-use components::{CanMove, Direction, Size, Sprite, SubTilePosition};
-use resources::{Assets, Camera, Map};
+use bindings::{self, Bindings, BindingsMenu};
+use components::{
+    sorted_bench, Attributes, Bench, CanMove, Direction, Elevation, Injury, Player, PlayerTeam,
+    Role, Size, Sprite, SubTilePosition, TilePosition,
+};
+use conditions::{Conditions, Weather};
+use resources::{
+    Action, ActionMenu, Assets, Camera, Map, MatchClock, MatchEvents, Score, Shootout,
+    Substitutions, Turn, TurnState,
+};
+use rules::Rules;
 
 fn get_direction(from: Point2<u32>, to: Point2<u32>) -> Option<Direction> {
-    if from.x < to.x && from.y == to.y {
-        Some(Direction::Right)
-    } else if from.x > to.x && from.y == to.y {
-        Some(Direction::Left)
-    } else if from.x == to.x && from.y < to.y {
-        Some(Direction::Down)
-    } else if from.x == to.x && from.y > to.y {
-        Some(Direction::Up)
-    } else {
-        None
+    match (to.x.cmp(&from.x), to.y.cmp(&from.y)) {
+        (Ordering::Greater, Ordering::Equal) => Some(Direction::Right),
+        (Ordering::Less, Ordering::Equal) => Some(Direction::Left),
+        (Ordering::Equal, Ordering::Greater) => Some(Direction::Down),
+        (Ordering::Equal, Ordering::Less) => Some(Direction::Up),
+        (Ordering::Greater, Ordering::Greater) => Some(Direction::DownRight),
+        (Ordering::Less, Ordering::Greater) => Some(Direction::DownLeft),
+        (Ordering::Greater, Ordering::Less) => Some(Direction::UpRight),
+        (Ordering::Less, Ordering::Less) => Some(Direction::UpLeft),
+        (Ordering::Equal, Ordering::Equal) => None,
     }
 }
 
@@ -43,28 +55,151 @@ fn get_path_directions(
     directions
 }
 
-fn get_path_draw_params(from: &Direction, to: &Option<Direction>) -> (u32, u32) {
-    match (from, to) {
+/// The path atlas is a square grid of cells, one per piece of path.
+const PATH_ATLAS_CELLS: u32 = 4;
+
+// Cells for building pieces of path with diagonal steps out of two halves,
+// each pointing right or down and right and rotated into place
+const STRAIGHT_HALF_CELL: (u32, u32) = (2, 2);
+const DIAGONAL_HALF_CELL: (u32, u32) = (3, 2);
+const DIAGONAL_ARROW_CELL: (u32, u32) = (0, 3);
+
+/// The clockwise angle of a direction from pointing right.
+fn direction_angle(direction: &Direction) -> f32 {
+    let eighths = match *direction {
+        Direction::Right => 0.0,
+        Direction::DownRight => 1.0,
+        Direction::Down => 2.0,
+        Direction::DownLeft => 3.0,
+        Direction::Left => 4.0,
+        Direction::UpLeft => 5.0,
+        Direction::Up => 6.0,
+        Direction::UpRight => 7.0,
+    };
+    eighths * PI / 4.0
+}
+
+fn is_diagonal(direction: &Direction) -> bool {
+    match *direction {
+        Direction::UpLeft | Direction::UpRight | Direction::DownLeft | Direction::DownRight => true,
+        Direction::Left | Direction::Up | Direction::Right | Direction::Down => false,
+    }
+}
+
+/// Half a piece of path, from the middle of the tile out towards `direction`.
+fn get_half_path_draw_params(direction: &Direction) -> (u32, u32, f32) {
+    if is_diagonal(direction) {
+        let (col, row) = DIAGONAL_HALF_CELL;
+        (col, row, direction_angle(direction) - PI / 4.0)
+    } else {
+        let (col, row) = STRAIGHT_HALF_CELL;
+        (col, row, direction_angle(direction))
+    }
+}
+
+/// Returns the column, row and rotation of each path atlas cell which makes
+/// up a piece of path.
+fn get_path_draw_params(from: &Direction, to: &Option<Direction>) -> Vec<(u32, u32, f32)> {
+    let cell = match (from, to) {
         (Direction::Left, Some(Direction::Right)) | (Direction::Right, Some(Direction::Left)) => {
             (0, 0)
         }
-        (Direction::Up, Some(Direction::Down)) | (Direction::Down, Some(Direction::Up)) => (64, 0),
-        (Direction::Left, None) => (128, 0),
-        (Direction::Up, None) => (192, 0),
-        (Direction::Right, None) => (128, 64),
-        (Direction::Down, None) => (192, 64),
-        (Direction::Left, Some(Direction::Up)) | (Direction::Up, Some(Direction::Left)) => (0, 64),
-        (Direction::Up, Some(Direction::Right)) | (Direction::Right, Some(Direction::Up)) => {
-            (64, 64)
-        }
+        (Direction::Up, Some(Direction::Down)) | (Direction::Down, Some(Direction::Up)) => (1, 0),
+        (Direction::Left, None) => (2, 0),
+        (Direction::Up, None) => (3, 0),
+        (Direction::Right, None) => (2, 1),
+        (Direction::Down, None) => (3, 1),
+        (Direction::Left, Some(Direction::Up)) | (Direction::Up, Some(Direction::Left)) => (0, 1),
+        (Direction::Up, Some(Direction::Right)) | (Direction::Right, Some(Direction::Up)) => (1, 1),
         (Direction::Left, Some(Direction::Down)) | (Direction::Down, Some(Direction::Left)) => {
-            (0, 128)
+            (0, 2)
         }
         (Direction::Down, Some(Direction::Right)) | (Direction::Right, Some(Direction::Down)) => {
-            (64, 128)
+            (1, 2)
+        }
+        // Anything else involves a diagonal step
+        (from, Some(to)) => {
+            return vec![
+                get_half_path_draw_params(from),
+                get_half_path_draw_params(to),
+            ];
         }
-        _ => unreachable!(),
+        (from, None) => {
+            // The arrow points away from where the path came from
+            let (col, row) = DIAGONAL_ARROW_CELL;
+            let angle = direction_angle(from) + PI - PI / 4.0;
+            return vec![get_half_path_draw_params(from), (col, row, angle)];
+        }
+    };
+
+    vec![(cell.0, cell.1, 0.0)]
+}
+
+/// How many of its own heights something must rise to be drawn twice as big.
+const AIRBORNE_GROWTH: f32 = 4.0;
+
+// How many raindrops or snowflakes are on screen at once, and how fast they
+// fall in pixels per second
+const RAINDROPS: u32 = 300;
+const RAIN_SPEED: f32 = 900.0;
+const SNOWFLAKES: u32 = 150;
+const SNOW_SPEED: f32 = 80.0;
+
+/// Draws rain or snow falling across the whole screen, blown along by the
+/// wind, as stretched copies of a plain white `image`.
+fn draw_weather(
+    ctx: &mut Context,
+    image: &graphics::Image,
+    conditions: &Conditions,
+) -> GameResult<()> {
+    let (count, speed) = match conditions.weather {
+        Weather::Dry => return Ok(()),
+        Weather::Wet => (RAINDROPS, RAIN_SPEED),
+        Weather::Snow => (SNOWFLAKES, SNOW_SPEED),
+    };
+    let screen = graphics::screen_coordinates(ctx);
+    let time = timer::duration_to_f64(timer::time_since_start(ctx)) as f32;
+    let wind = Vector2::new(conditions.wind.x as f32, conditions.wind.y as f32);
+    let velocity = (Vector2::new(0.0, 1.0) + wind * 0.5) * speed;
+    // Raindrops are thin streaks, turned to point the way they fall
+    let streak = velocity * 0.02;
+    let streak_angle = (-streak.x).atan2(streak.y);
+
+    let mut particles = SpriteBatch::new(image.clone());
+    for i in 0..count {
+        // Scatter each particle by hashing its index, so that it falls along
+        // the same line every time round
+        let hash = i.wrapping_mul(2_654_435_761);
+        let start = Vector2::new(
+            (hash % 1000) as f32 / 1000.0 * screen.w,
+            (hash / 1000 % 1000) as f32 / 1000.0 * screen.h,
+        );
+        let pos = start + velocity * time;
+        let pos = Point2::new(
+            (pos.x % screen.w + screen.w) % screen.w,
+            (pos.y % screen.h + screen.h) % screen.h,
+        );
+        let param = match conditions.weather {
+            Weather::Snow => graphics::DrawParam::default()
+                .dest(pos)
+                .offset(Point2::new(0.5, 0.5))
+                .scale(Vector2::new(4.0, 4.0))
+                .color(graphics::Color::new(1.0, 1.0, 1.0, 0.8)),
+            _ => graphics::DrawParam::default()
+                .dest(pos)
+                .offset(Point2::new(0.5, 0.0))
+                .rotation(streak_angle)
+                .scale(Vector2::new(1.0, streak.norm()))
+                .color(graphics::Color::new(0.7, 0.8, 1.0, 0.5)),
+        };
+        particles.add(param);
     }
+    graphics::draw(ctx, &particles, graphics::DrawParam::default())
+}
+
+/// The scale which stretches an image of `width` by `height` over one tile.
+fn tile_scale(map: &Map, width: f32, height: f32) -> Vector2<f32> {
+    map.tile_size().component_div(&Vector2::new(width, height))
 }
 
 pub struct RenderSystem<'a> {
@@ -80,19 +215,61 @@ impl<'a> RenderSystem<'a> {
 impl<'a, 'b> System<'b> for RenderSystem<'a> {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
     type SystemData = (
+        Entities<'b>,
         Fetch<'b, Assets>,
         Fetch<'b, Camera>,
         Fetch<'b, Map>,
+        Fetch<'b, Rules>,
+        Fetch<'b, Conditions>,
+        Fetch<'b, Turn>,
+        Fetch<'b, ActionMenu>,
+        Fetch<'b, Score>,
+        Fetch<'b, MatchClock>,
+        Fetch<'b, MatchEvents>,
+        Fetch<'b, Shootout>,
+        Fetch<'b, Substitutions>,
         ReadStorage<'b, CanMove>,
+        ReadStorage<'b, Player>,
+        ReadStorage<'b, Bench>,
+        ReadStorage<'b, Role>,
+        ReadStorage<'b, Attributes>,
+        ReadStorage<'b, Injury>,
+        ReadStorage<'b, TilePosition>,
         ReadStorage<'b, SubTilePosition>,
         ReadStorage<'b, Size>,
         ReadStorage<'b, Sprite>,
+        ReadStorage<'b, Elevation>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (assets, camera, map, can_moves, sub_tile_positions, _sizes, sprites) = data;
+        let (
+            entities,
+            assets,
+            camera,
+            map,
+            rules,
+            conditions,
+            turn,
+            action_menu,
+            score,
+            clock,
+            events,
+            shootout,
+            subs,
+            can_moves,
+            players,
+            benched,
+            roles,
+            attributes,
+            injuries,
+            tile_positions,
+            sub_tile_positions,
+            sizes,
+            sprites,
+            elevations,
+        ) = data;
 
-        graphics::set_transform(self.ctx, camera.mat);
+        graphics::set_transform(self.ctx, camera.mat());
         graphics::apply_transformations(self.ctx).unwrap();
 
         // // render map
@@ -100,6 +277,8 @@ impl<'a, 'b> System<'b> for RenderSystem<'a> {
 
         let mut tile_batch = SpriteBatch::new(assets.images[&tileset.name].clone());
 
+        // Tilesets drawn at a different size are stretched to fit the map
+        let tileset_scale = tile_scale(&map, tileset.tile_width as f32, tileset.tile_height as f32);
         let layer = &map.map.layers[0];
         for x in 0..map.map.width {
             for y in 0..map.map.height {
@@ -114,11 +293,11 @@ impl<'a, 'b> System<'b> for RenderSystem<'a> {
                     w: tileset.tile_width as f32 / tileset.images[0].width as f32,
                     h: tileset.tile_height as f32 / tileset.images[0].height as f32,
                 };
-                let dest = Point2::new(
-                    (x * map.map.tile_width) as f32,
-                    (y * map.map.tile_height) as f32,
-                );
-                let param = graphics::DrawParam::default().src(src).dest(dest);
+                let dest = map.tile_to_world(Point2::new(x, y));
+                let param = graphics::DrawParam::default()
+                    .src(src)
+                    .dest(dest)
+                    .scale(tileset_scale);
 
                 tile_batch.add(param);
             }
@@ -126,7 +305,13 @@ impl<'a, 'b> System<'b> for RenderSystem<'a> {
         graphics::draw(self.ctx, &tile_batch, graphics::DrawParam::default()).unwrap();
 
         // render highlights
-        let mut highlight_batch = SpriteBatch::new(assets.images["highlight"].clone());
+        let highlight_image = &assets.images["highlight"];
+        let highlight_scale = tile_scale(
+            &map,
+            f32::from(highlight_image.width()),
+            f32::from(highlight_image.height()),
+        );
+        let mut highlight_batch = SpriteBatch::new(highlight_image.clone());
         for (can_move,) in (&can_moves,).join() {
             for dest in &can_move.dests {
                 let src = graphics::Rect {
@@ -135,11 +320,33 @@ impl<'a, 'b> System<'b> for RenderSystem<'a> {
                     w: 1.0,
                     h: 1.0,
                 };
-                let dest = Point2::new(
-                    (dest.x * map.map.tile_width) as f32,
-                    (dest.y * map.map.tile_height) as f32,
-                );
-                let param = graphics::DrawParam::default().src(src).dest(dest);
+                let param = graphics::DrawParam::default()
+                    .src(src)
+                    .dest(map.tile_to_world(*dest))
+                    .scale(highlight_scale);
+
+                highlight_batch.add(param);
+            }
+
+            // Zones of control are shaded red over the top of the highlights
+            if rules.show_zones_of_control {
+                for zone in &can_move.costs.zones {
+                    let param = graphics::DrawParam::default()
+                        .dest(map.tile_to_world(*zone))
+                        .scale(highlight_scale)
+                        .color(graphics::Color::new(1.0, 0.2, 0.2, 0.5));
+
+                    highlight_batch.add(param);
+                }
+            }
+        }
+        // A goalkeeper choosing where to dive has no move of their own, but
+        // can only go somewhere in the goal mouth
+        if let TurnState::ShootoutDive { .. } = turn.state {
+            for dest in map.goal_mouth(PlayerTeam::Blue) {
+                let param = graphics::DrawParam::default()
+                    .dest(map.tile_to_world(dest))
+                    .scale(highlight_scale);
 
                 highlight_batch.add(param);
             }
@@ -148,30 +355,73 @@ impl<'a, 'b> System<'b> for RenderSystem<'a> {
 
         // render paths
         let path_image = &assets.images["path"];
+        let cell_width = f32::from(path_image.width()) / PATH_ATLAS_CELLS as f32;
+        let cell_height = f32::from(path_image.height()) / PATH_ATLAS_CELLS as f32;
+        let path_scale = tile_scale(&map, cell_width, cell_height);
         let mut path_batch = SpriteBatch::new(path_image.clone());
         for (can_move,) in (&can_moves,).join() {
             let directions = get_path_directions(can_move.start, &can_move.path);
             for (path, direction) in can_move.path.iter().zip(directions.iter()) {
-                let (src_x, src_y) = get_path_draw_params(&direction.0, &direction.1);
-                let src = graphics::Rect {
-                    x: src_x as f32 / f32::from(path_image.width()),
-                    y: src_y as f32 / f32::from(path_image.height()),
-                    w: 64.0 / f32::from(path_image.width()),
-                    h: 64.0 / f32::from(path_image.height()),
-                };
-                let dest = Point2::new(
-                    (path.x * map.map.tile_width) as f32,
-                    (path.y * map.map.tile_height) as f32,
-                );
-                let param = graphics::DrawParam::default().src(src).dest(dest);
-                path_batch.add(param);
+                // Drawn around the middle of the tile so that cells can be rotated
+                let dest = map.tile_to_world(*path) + map.tile_size() / 2.0;
+                for (col, row, rotation) in get_path_draw_params(&direction.0, &direction.1) {
+                    let cell_size = 1.0 / PATH_ATLAS_CELLS as f32;
+                    let src = graphics::Rect {
+                        x: col as f32 * cell_size,
+                        y: row as f32 * cell_size,
+                        w: cell_size,
+                        h: cell_size,
+                    };
+                    let param = graphics::DrawParam::default()
+                        .src(src)
+                        .dest(dest)
+                        .offset(Point2::new(0.5, 0.5))
+                        .rotation(rotation)
+                        .scale(path_scale);
+                    path_batch.add(param);
+                }
             }
         }
         graphics::draw(self.ctx, &path_batch, graphics::DrawParam::default()).unwrap();
 
+        // render the offside line for the team passing, along the edge of
+        // the tiles which are offside
+        let passer = match turn.state {
+            TurnState::SelectPass { player_id, .. }
+            | TurnState::SelectLoftedPass { player_id, .. } => Some(player_id),
+            _ => None,
+        };
+        if let Some(player_id) = passer {
+            if rules.offside {
+                let team = players.get(player_id).unwrap().team;
+                let ball = tile_positions.get(player_id).unwrap().pos;
+                let defenders: Vec<Point2<u32>> = (&players, &tile_positions)
+                    .join()
+                    .filter(|&(player, _)| player.team != team)
+                    .map(|(_, pos)| pos.pos)
+                    .collect();
+                let line = map.offside_line(team, ball, &defenders);
+                let edge = match team {
+                    PlayerTeam::Red => line + 1,
+                    PlayerTeam::Blue => line,
+                };
+                let x = map.tile_to_world(Point2::new(edge, 0)).x;
+                let param = graphics::DrawParam::default()
+                    .dest(Point2::new(x - 1.0, 0.0))
+                    .scale(Vector2::new(2.0, map.pixel_size().y))
+                    .color(graphics::Color::new(1.0, 0.85, 0.0, 0.8));
+                graphics::draw(self.ctx, &assets.images["white"], param).unwrap();
+            }
+        }
+
         // render sprite components
-        for (position, sprite) in (&sub_tile_positions, &sprites).join() {
+        for (entity, position, size, sprite) in
+            (&*entities, &sub_tile_positions, &sizes, &sprites).join()
+        {
             let texture = &assets.images[sprite.image_id];
+            let height = elevations
+                .get(entity)
+                .map_or(0.0, |elevation| elevation.height);
 
             let src = graphics::Rect {
                 x: 0.0,
@@ -179,10 +429,238 @@ impl<'a, 'b> System<'b> for RenderSystem<'a> {
                 w: 1.0,
                 h: 1.0,
             };
-            let dest = Point2::new(position.pos.x, position.pos.y);
-            let param = graphics::DrawParam::default().src(src).dest(dest);
+            let mut dest = Point2::new(position.pos.x, position.pos.y);
+            let mut scale = Vector2::new(
+                size.width / f32::from(texture.width()),
+                size.height / f32::from(texture.height()),
+            );
+
+            if height > 0.0 {
+                // Things in the air cast a shadow which shrinks as they rise,
+                // and are drawn raised and larger as if closer to the camera
+                let lift = height / (size.height * AIRBORNE_GROWTH);
+                let shadow_centre = Point2::new(
+                    position.pos.x + size.width / 2.0,
+                    position.pos.y + size.height,
+                );
+                let shadow_param = graphics::DrawParam::default()
+                    .dest(shadow_centre)
+                    .scale(Vector2::new(
+                        size.width * 0.3 / (1.0 + lift),
+                        size.height * 0.1 / (1.0 + lift),
+                    ))
+                    .color(graphics::Color::new(0.0, 0.0, 0.0, 0.4));
+                graphics::draw(self.ctx, &assets.meshes["shadow"], shadow_param).unwrap();
+
+                scale *= 1.0 + lift;
+                dest.x -= size.width * lift / 2.0;
+                dest.y -= height + size.height * lift / 2.0;
+            }
+            let param = graphics::DrawParam::default()
+                .src(src)
+                .dest(dest)
+                .scale(scale);
 
             graphics::draw(self.ctx, texture, param).unwrap();
+
+            // Injured players get a cross by their head, red if they can't
+            // play on
+            if let Some(injury) = injuries.get(entity) {
+                let color = if injury.serious {
+                    graphics::Color::new(0.9, 0.1, 0.1, 1.0)
+                } else {
+                    graphics::Color::new(1.0, 0.6, 0.0, 1.0)
+                };
+                let arm = size.width / 10.0;
+                let centre = Point2::new(dest.x + size.width * 0.8, dest.y + size.height * 0.2);
+                for &(width, height) in &[(arm * 3.0, arm), (arm, arm * 3.0)] {
+                    let param = graphics::DrawParam::default()
+                        .dest(Point2::new(centre.x - width / 2.0, centre.y - height / 2.0))
+                        .scale(Vector2::new(width, height))
+                        .color(color);
+                    graphics::draw(self.ctx, &assets.images["white"], param).unwrap();
+                }
+            }
+        }
+
+        // Everything from here on is drawn in screen space
+        graphics::set_transform(self.ctx, Matrix4::identity());
+        graphics::apply_transformations(self.ctx).unwrap();
+
+        // render the weather over the pitch, darkening it in the wet and
+        // whitening it in the snow, but under the menus so they can be read
+        let tint = match conditions.weather {
+            Weather::Dry => None,
+            Weather::Wet => Some(graphics::Color::new(0.0, 0.05, 0.15, 0.35)),
+            Weather::Snow => Some(graphics::Color::new(0.9, 0.95, 1.0, 0.2)),
+        };
+        let white_image = &assets.images["white"];
+        if let Some(tint) = tint {
+            let screen = graphics::screen_coordinates(self.ctx);
+            let param = graphics::DrawParam::default()
+                .dest(Point2::new(screen.x, screen.y))
+                .scale(Vector2::new(screen.w, screen.h))
+                .color(tint);
+            graphics::draw(self.ctx, white_image, param).unwrap();
+        }
+        draw_weather(self.ctx, white_image, &conditions).unwrap();
+
+        // render action menu
+        if let TurnState::ActionMenu { .. } = turn.state {
+            for (i, action) in action_menu.actions.iter().enumerate() {
+                let mut text = graphics::Text::new(action.label());
+                text.set_font(graphics::Font::default(), graphics::Scale::uniform(24.0));
+                let color = if i == action_menu.selected {
+                    graphics::Color::new(1.0, 0.85, 0.0, 1.0)
+                } else {
+                    graphics::WHITE
+                };
+                graphics::draw(self.ctx, &text, (ActionMenu::item_origin(i), color)).unwrap();
+            }
+        }
+
+        // render score and time, then any shootout with each kick as O if
+        // it went in or X if not, and whatever last happened underneath
+        let screen_width = graphics::screen_coordinates(self.ctx).w;
+        let half = if clock.half % 2 == 1 { "1st" } else { "2nd" };
+        let time = match turn.state {
+            TurnState::FullTime => "Full time".to_string(),
+            _ if shootout.started => "Penalties".to_string(),
+            _ if clock.in_extra_time() => format!(
+                "{} half of extra time {}/{}",
+                half, clock.elapsed, rules.extra_time_length
+            ),
+            _ => format!("{} half {}/{}", half, clock.elapsed, rules.half_length),
+        };
+        let mut lines = vec![format!(
+            "Red {} - {} Blue    {}    {}",
+            score.red,
+            score.blue,
+            time,
+            conditions.describe()
+        )];
+        if shootout.started {
+            let kicks = |team| {
+                shootout
+                    .kicks(team)
+                    .iter()
+                    .map(|&scored| if scored { "O" } else { "X" })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            };
+            lines.push(format!(
+                "Penalties: Red {} ({}) - ({}) {} Blue",
+                shootout.scored(PlayerTeam::Red),
+                kicks(PlayerTeam::Red),
+                kicks(PlayerTeam::Blue),
+                shootout.scored(PlayerTeam::Blue)
+            ));
+        }
+        if let Some(event) = events.events.last() {
+            lines.push(event.describe());
+        }
+        for (i, line) in lines.into_iter().enumerate() {
+            let mut text = graphics::Text::new(line);
+            text.set_font(graphics::Font::default(), graphics::Scale::uniform(24.0));
+            let x = (screen_width - text.width(self.ctx) as f32) / 2.0;
+            let dest = Point2::new(x, 16.0 + 32.0 * i as f32);
+            graphics::draw(self.ctx, &text, (dest, graphics::WHITE)).unwrap();
+        }
+
+        // render each team's bench down the right, with the reserve being
+        // picked to come on highlighted
+        let picking = match turn.state {
+            TurnState::SelectSubstitute {
+                player_id,
+                selected,
+            } => Some((players.get(player_id).unwrap().team, selected)),
+            _ => None,
+        };
+        let mut y = 48.0;
+        for &team in &[PlayerTeam::Red, PlayerTeam::Blue] {
+            let left = rules.max_substitutions.saturating_sub(subs.made(team));
+            let mut lines = vec![(
+                format!("{} bench, {} subs left", team.name(), left),
+                graphics::WHITE,
+            )];
+            let bench = sorted_bench(
+                (&*entities, &players, &benched)
+                    .join()
+                    .filter(|&(_, reserve, _)| reserve.team == team)
+                    .map(|(reserve_id, _, _)| reserve_id)
+                    .collect(),
+            );
+            for (i, &reserve_id) in bench.iter().enumerate() {
+                let role = match *roles.get(reserve_id).unwrap() {
+                    Role::Outfield => "Outfield",
+                    Role::Goalkeeper => "Keeper",
+                };
+                let skills = attributes.get(reserve_id).unwrap();
+                let color = if picking == Some((team, i)) {
+                    graphics::Color::new(1.0, 0.85, 0.0, 1.0)
+                } else {
+                    graphics::WHITE
+                };
+                lines.push((
+                    format!(
+                        "{}  P{} S{} C{} H{}",
+                        role, skills.passing, skills.shooting, skills.control, skills.handling
+                    ),
+                    color,
+                ));
+            }
+            for (line, color) in lines {
+                let mut text = graphics::Text::new(line);
+                text.set_font(graphics::Font::default(), graphics::Scale::uniform(20.0));
+                let x = screen_width - text.width(self.ctx) as f32 - 8.0;
+                graphics::draw(self.ctx, &text, (Point2::new(x, y), color)).unwrap();
+                y += 24.0;
+            }
+            y += 16.0;
         }
     }
 }
+
+/// Draws the key rebinding screen over the top of the game, darkening it
+/// with a plain white `image` stretched over the screen.
+pub fn draw_bindings_menu(
+    ctx: &mut Context,
+    image: &graphics::Image,
+    bindings: &Bindings,
+    menu: &BindingsMenu,
+) -> GameResult<()> {
+    let screen = graphics::screen_coordinates(ctx);
+    let background = graphics::DrawParam::default()
+        .dest(Point2::new(screen.x, screen.y))
+        .scale(Vector2::new(screen.w, screen.h))
+        .color(graphics::Color::new(0.0, 0.0, 0.0, 0.8));
+    graphics::draw(ctx, image, background)?;
+
+    let help = if menu.capturing {
+        "Press a key to add it to the selected action, or Escape to cancel"
+    } else {
+        "Up/Down: choose action   Return: add key   Backspace: clear   Escape: save and close"
+    };
+    let mut help_text = graphics::Text::new(help);
+    help_text.set_font(graphics::Font::default(), graphics::Scale::uniform(24.0));
+    graphics::draw(ctx, &help_text, (Point2::new(64.0, 48.0), graphics::WHITE))?;
+
+    for (i, action) in Action::ALL.iter().enumerate() {
+        let keys: Vec<String> = bindings
+            .keys(*action)
+            .iter()
+            .map(|key| bindings::key_name(*key))
+            .collect();
+        let mut text = graphics::Text::new(format!("{:<16}{}", action.name(), keys.join(", ")));
+        text.set_font(graphics::Font::default(), graphics::Scale::uniform(24.0));
+        let color = if i == menu.selected {
+            graphics::Color::new(1.0, 0.85, 0.0, 1.0)
+        } else {
+            graphics::WHITE
+        };
+        let dest = Point2::new(64.0, 112.0 + 36.0 * i as f32);
+        graphics::draw(ctx, &text, (dest, color))?;
+    }
+
+    Ok(())
+}
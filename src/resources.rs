@@ -1,34 +1,114 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::default::Default;
 use std::string::String;
 
-use ggez::graphics::Image;
-use nalgebra::{Matrix4, Vector3};
+use ggez::graphics::{Image, Mesh};
+use nalgebra::{Matrix4, Point2, Vector2, Vector3};
 use specs::Entity;
 use tiled;
 
+use components::{Formation, PlayerTeam};
+
 pub struct Assets {
     pub images: HashMap<String, Image>,
+    /// Shapes built once up front, to be scaled and coloured when drawn.
+    pub meshes: HashMap<String, Mesh>,
 }
 
 impl Assets {
     pub fn new() -> Self {
         Self {
             images: HashMap::new(),
+            meshes: HashMap::new(),
         }
     }
 }
 
+const MAX_ZOOM: f32 = 2.0;
+
 pub struct Camera {
-    pub mat: Matrix4<f32>,
+    /// The world position shown at the top left of the screen.
+    pub pos: Point2<f32>,
+    pub viewport: Vector2<f32>,
+    /// Screen pixels per world pixel.
+    pub zoom: f32,
+    /// The zoom at which the whole world fits on screen.
+    pub min_zoom: f32,
+    /// The zoom to return to when leaving the fit-to-pitch view.
+    pub unfitted_zoom: Option<f32>,
 }
 
 impl Camera {
-    pub fn new() -> Self {
+    pub fn new(viewport: Vector2<f32>, world_size: Vector2<f32>) -> Self {
+        let min_zoom = (viewport.x / world_size.x)
+            .min(viewport.y / world_size.y)
+            .min(1.0);
         Camera {
-            mat: Matrix4::new_translation(&Vector3::new(0.0, 0.0, 0.0)),
+            pos: Point2::new(0.0, 0.0),
+            viewport,
+            zoom: 1.0,
+            min_zoom,
+            unfitted_zoom: None,
+        }
+    }
+
+    pub fn mat(&self) -> Matrix4<f32> {
+        Matrix4::new_nonuniform_scaling(&Vector3::new(self.zoom, self.zoom, 1.0))
+            * Matrix4::new_translation(&Vector3::new(-self.pos.x, -self.pos.y, 0.0))
+    }
+
+    /// The size of the visible part of the world.
+    pub fn view_size(&self) -> Vector2<f32> {
+        self.viewport / self.zoom
+    }
+
+    pub fn screen_to_world(&self, pos: Point2<f32>) -> Point2<f32> {
+        self.pos + pos.coords / self.zoom
+    }
+
+    pub fn world_to_screen(&self, pos: Point2<f32>) -> Point2<f32> {
+        Point2::from_coordinates((pos - self.pos) * self.zoom)
+    }
+
+    /// Centres the view on `target`.
+    pub fn pos_centred_on(&self, target: Point2<f32>) -> Point2<f32> {
+        target - self.view_size() / 2.0
+    }
+
+    /// Changes the zoom, keeping `anchor` at the same place on screen.
+    pub fn zoom_around(&mut self, zoom: f32, anchor: Point2<f32>) {
+        let screen_anchor = self.world_to_screen(anchor);
+        self.zoom = zoom.max(self.min_zoom).min(MAX_ZOOM);
+        self.pos = anchor - screen_anchor.coords / self.zoom;
+    }
+
+    /// Toggles between viewing the whole world and the previous zoom.
+    pub fn toggle_fit(&mut self, anchor: Point2<f32>) {
+        match self.unfitted_zoom.take() {
+            Some(zoom) => self.zoom_around(zoom, anchor),
+            None => {
+                self.unfitted_zoom = Some(self.zoom);
+                let min_zoom = self.min_zoom;
+                self.zoom_around(min_zoom, anchor);
+            }
         }
     }
+
+    /// Keeps the view within a world of `world_size`, centring it if the
+    /// whole world fits on screen.
+    pub fn clamp(&mut self, world_size: Vector2<f32>) {
+        let view_size = self.view_size();
+        self.pos.x = clamp_axis(self.pos.x, view_size.x, world_size.x);
+        self.pos.y = clamp_axis(self.pos.y, view_size.y, world_size.y);
+    }
+}
+
+fn clamp_axis(pos: f32, view: f32, world: f32) -> f32 {
+    if world <= view {
+        (world - view) / 2.0
+    } else {
+        pos.max(0.0).min(world - view)
+    }
 }
 
 #[derive(Debug)]
@@ -36,33 +116,121 @@ pub struct DeltaTime {
     pub dt: f32,
 }
 
+/// A logical input, which may be bound to any number of keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Action {
+    CursorUp,
+    CursorDown,
+    CursorLeft,
+    CursorRight,
+    CameraUp,
+    CameraDown,
+    CameraLeft,
+    CameraRight,
+    ZoomIn,
+    ZoomOut,
+    FitPitch,
+    Select,
+    Cancel,
+    EndTurn,
+    LoadTurn,
+    Bindings,
+}
+
+impl Action {
+    pub const ALL: [Action; 16] = [
+        Action::CursorUp,
+        Action::CursorDown,
+        Action::CursorLeft,
+        Action::CursorRight,
+        Action::CameraUp,
+        Action::CameraDown,
+        Action::CameraLeft,
+        Action::CameraRight,
+        Action::ZoomIn,
+        Action::ZoomOut,
+        Action::FitPitch,
+        Action::Select,
+        Action::Cancel,
+        Action::EndTurn,
+        Action::LoadTurn,
+        Action::Bindings,
+    ];
+
+    pub fn name(self) -> String {
+        format!("{:?}", self)
+    }
+
+    pub fn from_name(name: &str) -> Option<Action> {
+        Action::ALL
+            .iter()
+            .cloned()
+            .find(|action| action.name() == name)
+    }
+}
+
 #[derive(Debug)]
 pub struct Input {
-    pub up: bool,
-    pub down: bool,
-    pub left: bool,
-    pub right: bool,
-    pub select: bool,
-    pub cancel: bool,
-    pub w: bool,
-    pub a: bool,
-    pub s: bool,
-    pub d: bool,
+    held: HashSet<Action>,
+    pressed: HashSet<Action>,
+    /// Analogue cursor stick, with up being positive y.
+    pub stick: Vector2<f32>,
+    /// Analogue camera stick, with up being positive y.
+    pub camera_stick: Vector2<f32>,
+    /// Mouse wheel movement since the last frame, with up being positive.
+    pub wheel: f32,
+    pub mouse_pos: Point2<f32>,
+    /// Whether the cursor should follow the mouse, i.e. it has been used more
+    /// recently than the keyboard.
+    pub mouse_active: bool,
+    /// Whether the left button is down, tracing a path as the mouse moves.
+    pub mouse_dragging: bool,
+    /// A click which will become a select once the cursor reaches the mouse.
+    pub mouse_clicked: bool,
+}
+
+impl Input {
+    /// Starts holding `action`, which also counts as a press this frame.
+    pub fn press(&mut self, action: Action) {
+        self.held.insert(action);
+        self.pressed.insert(action);
+    }
+
+    pub fn release(&mut self, action: Action) {
+        self.held.remove(&action);
+    }
+
+    /// Presses `action` for this frame only, without holding it.
+    pub fn trigger(&mut self, action: Action) {
+        self.pressed.insert(action);
+    }
+
+    pub fn is_held(&self, action: Action) -> bool {
+        self.held.contains(&action)
+    }
+
+    pub fn was_pressed(&self, action: Action) -> bool {
+        self.pressed.contains(&action)
+    }
+
+    pub fn end_frame(&mut self) {
+        self.pressed.clear();
+        self.wheel = 0.0;
+    }
 }
 
 impl Default for Input {
     fn default() -> Self {
         Self {
-            up: false,
-            down: false,
-            left: false,
-            right: false,
-            select: false,
-            cancel: false,
-            w: false,
-            a: false,
-            s: false,
-            d: false,
+            held: HashSet::new(),
+            pressed: HashSet::new(),
+            stick: Vector2::new(0.0, 0.0),
+            camera_stick: Vector2::new(0.0, 0.0),
+            wheel: 0.0,
+            mouse_pos: Point2::new(0.0, 0.0),
+            mouse_active: false,
+            mouse_dragging: false,
+            mouse_clicked: false,
         }
     }
 }
@@ -71,6 +239,271 @@ pub struct Map {
     pub map: tiled::Map,
 }
 
+// The goals, goal areas and penalty areas of a real pitch, as fractions of
+// its length and width
+const GOAL_WIDTH: f32 = 7.32 / 68.0;
+const GOAL_AREA_DEPTH: f32 = 5.5 / 105.0;
+const PENALTY_AREA_DEPTH: f32 = 16.5 / 105.0;
+const PENALTY_AREA_WIDTH: f32 = 40.3 / 68.0;
+const PENALTY_SPOT_DISTANCE: f32 = 11.0 / 105.0;
+
+/// Conversions between tile coordinates and world pixels all go through the
+/// map, so that its tile size is the only one in use.
+impl Map {
+    pub fn size(&self) -> Vector2<u32> {
+        Vector2::new(self.map.width, self.map.height)
+    }
+
+    pub fn tile_size(&self) -> Vector2<f32> {
+        Vector2::new(self.map.tile_width as f32, self.map.tile_height as f32)
+    }
+
+    /// The size of the whole map in pixels.
+    pub fn pixel_size(&self) -> Vector2<f32> {
+        Vector2::new(self.map.width as f32, self.map.height as f32).component_mul(&self.tile_size())
+    }
+
+    /// The world position of the top left of a tile.
+    pub fn tile_to_world(&self, tile_pos: Point2<u32>) -> Point2<f32> {
+        Point2::new(
+            (tile_pos.x * self.map.tile_width) as f32,
+            (tile_pos.y * self.map.tile_height) as f32,
+        )
+    }
+
+    /// Whether a tile is inside the field of play. The outermost tiles of
+    /// the map lie beyond the touchlines and goal lines.
+    pub fn in_field(&self, tile: Point2<u32>) -> bool {
+        tile.x > 0 && tile.y > 0 && tile.x < self.map.width - 1 && tile.y < self.map.height - 1
+    }
+
+    pub fn centre_spot(&self) -> Point2<u32> {
+        Point2::new(self.map.width / 2, self.map.height / 2)
+    }
+
+    /// Where a player on `team` lines up in `formation`, inside their own
+    /// half.
+    pub fn formation_tile(&self, team: PlayerTeam, formation: Formation) -> Point2<u32> {
+        let goal_line = self.goal_line(team) as f32;
+        let halfway = self.centre_spot().x as f32;
+        let x = (goal_line + (halfway - goal_line) * formation.depth).round() as u32;
+        let y = (1.0 + (self.map.height - 3) as f32 * formation.width).round() as u32;
+        Point2::new(x.max(1).min(self.map.width - 2), y)
+    }
+
+    /// Where a throw-in is taken after the ball goes out over `tile`: the
+    /// nearest point on the touchline inside the field.
+    pub fn throw_in_spot(&self, tile: Point2<u32>) -> Point2<u32> {
+        Point2::new(
+            tile.x.max(1).min(self.map.width - 2),
+            tile.y.max(1).min(self.map.height - 2),
+        )
+    }
+
+    /// Where a goal kick is taken in front of the goal that `team` defends.
+    pub fn goal_kick_spot(&self, team: PlayerTeam) -> Point2<u32> {
+        let depth = (self.map.width as f32 * GOAL_AREA_DEPTH).floor() as u32;
+        let x = match team {
+            PlayerTeam::Red => self.goal_line(team) + depth,
+            PlayerTeam::Blue => self.goal_line(team) - depth,
+        };
+        Point2::new(x, self.map.height / 2)
+    }
+
+    /// The corner of the field by the goal that `team` defends which is
+    /// nearest to `tile`.
+    pub fn corner_spot(&self, team: PlayerTeam, tile: Point2<u32>) -> Point2<u32> {
+        let x = match team {
+            PlayerTeam::Red => 1,
+            PlayerTeam::Blue => self.map.width - 2,
+        };
+        let y = if tile.y < self.map.height / 2 {
+            1
+        } else {
+            self.map.height - 2
+        };
+        Point2::new(x, y)
+    }
+
+    /// The column of tiles along the goal line that `team` defends. Red
+    /// defend the left-hand goal and Blue the right.
+    pub fn goal_line(&self, team: PlayerTeam) -> u32 {
+        match team {
+            PlayerTeam::Red => 0,
+            PlayerTeam::Blue => self.map.width - 1,
+        }
+    }
+
+    /// The tiles on the goal line between the posts of the goal that `team`
+    /// defends.
+    pub fn goal_mouth(&self, team: PlayerTeam) -> Vec<Point2<u32>> {
+        let width = ((self.map.height as f32 * GOAL_WIDTH).round() as u32).max(1);
+        let top = (self.map.height - width) / 2;
+        let x = self.goal_line(team);
+        (top..top + width).map(|y| Point2::new(x, y)).collect()
+    }
+
+    /// The column of tiles along the offside line when `team` attack with
+    /// the ball on `ball` against defenders on `defenders`. It is level with
+    /// the second-last defender, or with the ball or halfway line if either
+    /// of those is further back.
+    pub fn offside_line(
+        &self,
+        team: PlayerTeam,
+        ball: Point2<u32>,
+        defenders: &[Point2<u32>],
+    ) -> u32 {
+        let goal_line = self.goal_line(team.opponents());
+        let depth = |x: u32| (x as i32 - goal_line as i32).abs() as u32;
+        let mut depths: Vec<u32> = defenders.iter().map(|tile| depth(tile.x)).collect();
+        depths.sort();
+        let line = depths
+            .get(1)
+            .cloned()
+            .unwrap_or(0)
+            .min(depth(ball.x))
+            .min(depth(self.centre_spot().x));
+        match team {
+            PlayerTeam::Red => goal_line - line,
+            PlayerTeam::Blue => goal_line + line,
+        }
+    }
+
+    /// Whether a player on `team` at `tile` is beyond the offside line
+    /// `line`.
+    pub fn is_offside(&self, team: PlayerTeam, line: u32, tile: Point2<u32>) -> bool {
+        match team {
+            PlayerTeam::Red => tile.x > line,
+            PlayerTeam::Blue => tile.x < line,
+        }
+    }
+
+    /// Where penalties against `team` are taken from.
+    pub fn penalty_spot(&self, team: PlayerTeam) -> Point2<u32> {
+        let distance = (self.map.width as f32 * PENALTY_SPOT_DISTANCE).round() as u32;
+        let x = match team {
+            PlayerTeam::Red => self.goal_line(team) + distance,
+            PlayerTeam::Blue => self.goal_line(team) - distance,
+        };
+        Point2::new(x, self.map.height / 2)
+    }
+
+    /// Whether a tile is inside the penalty area in front of the goal that
+    /// `team` defends.
+    pub fn in_penalty_area(&self, team: PlayerTeam, tile: Point2<u32>) -> bool {
+        let depth = (self.map.width as f32 * PENALTY_AREA_DEPTH).round() as i32;
+        let width = (self.map.height as f32 * PENALTY_AREA_WIDTH).round() as u32;
+        let top = (self.map.height - width) / 2;
+        (tile.x as i32 - self.goal_line(team) as i32).abs() < depth
+            && tile.y >= top
+            && tile.y < top + width
+    }
+
+    /// The tile containing a world position, if it is on the map.
+    pub fn world_to_tile(&self, pos: Point2<f32>) -> Option<Point2<u32>> {
+        let tile = pos.coords.component_div(&self.tile_size());
+        let (x, y) = (tile.x.floor(), tile.y.floor());
+        if x >= 0.0 && y >= 0.0 && (x as u32) < self.map.width && (y as u32) < self.map.height {
+            Some(Point2::new(x as u32, y as u32))
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuAction {
+    Run,
+    Pass,
+    LoftedPass,
+    ThroughBall,
+    KnockOn,
+    Shoot,
+    Substitute,
+}
+
+impl MenuAction {
+    pub fn label(self) -> &'static str {
+        match self {
+            MenuAction::Run => "Run",
+            MenuAction::Pass => "Pass",
+            MenuAction::LoftedPass => "Lofted pass",
+            MenuAction::ThroughBall => "Through ball",
+            MenuAction::KnockOn => "Knock on",
+            MenuAction::Shoot => "Shoot",
+            MenuAction::Substitute => "Substitute",
+        }
+    }
+}
+
+const MENU_ORIGIN: (f32, f32) = (8.0, 48.0);
+const MENU_ITEM_SIZE: (f32, f32) = (240.0, 32.0);
+
+/// The actions available to the selected player, laid out in screen space.
+pub struct ActionMenu {
+    pub actions: Vec<MenuAction>,
+    pub selected: usize,
+}
+
+impl ActionMenu {
+    pub fn new() -> Self {
+        Self {
+            actions: Vec::new(),
+            selected: 0,
+        }
+    }
+
+    pub fn item_origin(index: usize) -> Point2<f32> {
+        Point2::new(
+            MENU_ORIGIN.0,
+            MENU_ORIGIN.1 + MENU_ITEM_SIZE.1 * index as f32,
+        )
+    }
+
+    pub fn item_at(&self, pos: Point2<f32>) -> Option<usize> {
+        (0..self.actions.len()).find(|&i| {
+            let offset: Vector2<f32> = pos - Self::item_origin(i);
+            offset.x >= 0.0
+                && offset.x < MENU_ITEM_SIZE.0
+                && offset.y >= 0.0
+                && offset.y < MENU_ITEM_SIZE.1
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RestartKind {
+    KickOff,
+    ThrowIn,
+    GoalKick,
+    Corner,
+    FreeKick,
+    /// A free kick which can't be shot straight at goal.
+    IndirectFreeKick,
+    Penalty,
+}
+
+/// How play starts again after it has stopped: which team takes what kind
+/// of restart, and from where.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Restart {
+    pub kind: RestartKind,
+    pub team: PlayerTeam,
+    pub spot: Point2<u32>,
+}
+
+/// A restart whose taker has been put in place with the ball but has not
+/// played it yet. Until they do, play is stopped: nobody else can do
+/// anything but be substituted, and the taker can only pass, or shoot from
+/// free kicks and penalties.
+#[derive(Default)]
+pub struct PendingRestart {
+    pub taker: Option<(Entity, RestartKind)>,
+    /// A restart given for something that happened during play, to be set
+    /// up once everything has stopped moving.
+    pub awarded: Option<Restart>,
+}
+
 #[derive(Debug, Clone)]
 pub enum TurnState {
     SelectPlayer,
@@ -78,9 +511,476 @@ pub enum TurnState {
     SelectRun { player_id: Entity },
     Running { player_id: Entity },
     SelectPass { player_id: Entity, ball_id: Entity },
+    SelectLoftedPass { player_id: Entity, ball_id: Entity },
     Passing { player_id: Entity, ball_id: Entity },
+    SelectThroughBall { player_id: Entity, ball_id: Entity },
+    SelectReceiver { player_id: Entity, ball_id: Entity },
+    ThroughBall { player_id: Entity, ball_id: Entity },
+    SelectKnockOn { player_id: Entity, ball_id: Entity },
+    KnockOn { player_id: Entity, ball_id: Entity },
+    SelectShot { player_id: Entity, ball_id: Entity },
+    /// Players are put in place for a restart.
+    Restart { restart: Restart },
+    /// Both teams line up in their own halves for `team` to kick off.
+    KickOff { team: PlayerTeam },
+    /// Choosing which of the bench, in order, comes on for `player_id`.
+    SelectSubstitute { player_id: Entity, selected: usize },
+    /// `reserve_id` takes the place of `player_id`.
+    Substitution { player_id: Entity, reserve_id: Entity },
+    /// The match has finished level, so it goes to penalties, all taken at
+    /// the right-hand goal.
+    Shootout,
+    /// `team`, in goal for the next kick of the shootout, choosing where
+    /// their goalkeeper dives before they know who is taking it.
+    ShootoutDive { team: PlayerTeam },
+    /// Choosing who takes `team`'s next kick in the shootout.
+    ShootoutTaker { team: PlayerTeam },
+    /// Choosing where `taker_id` aims.
+    ShootoutShot { taker_id: Entity },
+    /// `taker_id` has aimed at `target`, and the kick is taken once the side
+    /// in goal reveals where their goalkeeper dived.
+    ShootoutReveal { taker_id: Entity, target: Point2<u32> },
+    /// `taker_id` shoots at `target` from the penalty spot.
+    PenaltyKick { taker_id: Entity, target: Point2<u32> },
+    /// The match is over.
+    FullTime,
 }
 
 pub struct Turn {
     pub state: TurnState,
+    /// The side whose turn it is when playing by turn file. Red have the
+    /// first, and each turn exported or played out hands it to the other.
+    pub side: PlayerTeam,
+}
+
+/// An action taken during a turn, identified by tile positions rather than
+/// entities so that it can be replayed on another client.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TurnAction {
+    Run {
+        from: Point2<u32>,
+        path: Vec<Point2<u32>>,
+    },
+    Pass {
+        from: Point2<u32>,
+        path: Vec<Point2<u32>>,
+    },
+    LoftedPass {
+        from: Point2<u32>,
+        target: Point2<u32>,
+    },
+    ThroughBall {
+        from: Point2<u32>,
+        path: Vec<Point2<u32>>,
+        receiver: Point2<u32>,
+    },
+    KnockOn {
+        from: Point2<u32>,
+        path: Vec<Point2<u32>>,
+    },
+    Shot {
+        from: Point2<u32>,
+        target: Point2<u32>,
+    },
+    /// The player at `from` is replaced by the `reserve`th player on their
+    /// bench.
+    Substitute { from: Point2<u32>, reserve: usize },
+    /// The goalkeeper facing the next kick in a shootout has chosen where to
+    /// dive, which is kept hidden behind a `commitment` until the kick has
+    /// been aimed.
+    ShootoutDive { commitment: u64 },
+    /// The player at `from` takes a kick in a shootout at `target`.
+    ShootoutKick {
+        from: Point2<u32>,
+        target: Point2<u32>,
+    },
+    /// The goalkeeper's dive to `dive`, with the `salt` which went into its
+    /// commitment.
+    ShootoutReveal { dive: Point2<u32>, salt: u64 },
+}
+
+/// Something worth telling both players about.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MatchEvent {
+    /// Scored by `team`.
+    Goal { team: PlayerTeam },
+    /// A shot stopped by `team`'s goalkeeper.
+    Saved { team: PlayerTeam },
+    /// A shot blocked by one of `team`'s players.
+    Blocked { team: PlayerTeam },
+    /// Put over the goal line by `team`, the attackers, so that the
+    /// defenders get a goal kick.
+    Wide { team: PlayerTeam },
+    /// Given to `team` after the defenders put the ball behind their goal.
+    Corner { team: PlayerTeam },
+    /// Given to `team` after the other side put the ball over a touchline.
+    ThrowIn { team: PlayerTeam },
+    /// A change made by `team`.
+    Substitution { team: PlayerTeam },
+    /// A pass to one of `team`'s players who was offside.
+    Offside { team: PlayerTeam },
+    /// Committed by one of `team`'s players, in their own penalty area if
+    /// `penalty` is set.
+    Foul {
+        team: PlayerTeam,
+        penalty: bool,
+        card: Option<Card>,
+    },
+    /// One of `team`'s players hurt in a challenge.
+    Injury {
+        team: PlayerTeam,
+        effect: InjuryEffect,
+    },
+    /// The match is level at the end of normal time.
+    ExtraTime,
+    FullTime,
+    /// A kick taken by `team` in a shootout.
+    ShootoutKick {
+        team: PlayerTeam,
+        scored: bool,
+    },
+    ShootoutWon { team: PlayerTeam },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum InjuryEffect {
+    /// Weakened but able to carry on.
+    Knock,
+    /// Unable to play on until they are substituted.
+    Substitution,
+    /// Unable to play on with no substitutes left, so the team is a player
+    /// down.
+    Off,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Card {
+    Yellow,
+    /// A second yellow card, and so a red one.
+    SecondYellow,
+    Red,
+}
+
+impl Card {
+    pub fn sends_off(self) -> bool {
+        match self {
+            Card::Yellow => false,
+            Card::SecondYellow | Card::Red => true,
+        }
+    }
+}
+
+impl MatchEvent {
+    pub fn describe(self) -> String {
+        match self {
+            MatchEvent::Goal { team } => format!("Goal for {}!", team.name()),
+            MatchEvent::Saved { team } => format!("Saved by the {} goalkeeper", team.name()),
+            MatchEvent::Blocked { team } => format!("Blocked by {}", team.name()),
+            MatchEvent::Wide { team } => format!(
+                "Wide from {}, goal kick to {}",
+                team.name(),
+                team.opponents().name()
+            ),
+            MatchEvent::Corner { team } => format!("Corner to {}", team.name()),
+            MatchEvent::ThrowIn { team } => format!("Throw-in to {}", team.name()),
+            MatchEvent::Substitution { team } => format!("Substitution for {}", team.name()),
+            MatchEvent::Offside { team } => format!(
+                "Offside against {}, indirect free kick to {}",
+                team.name(),
+                team.opponents().name()
+            ),
+            MatchEvent::Foul {
+                team,
+                penalty,
+                card,
+            } => {
+                let restart = if penalty { "penalty" } else { "free kick" };
+                let card = match card {
+                    None => "",
+                    Some(Card::Yellow) => ", yellow card",
+                    Some(Card::SecondYellow) => ", second yellow card and sent off",
+                    Some(Card::Red) => ", red card and sent off",
+                };
+                format!(
+                    "Foul by {}, {} to {}{}",
+                    team.name(),
+                    restart,
+                    team.opponents().name(),
+                    card
+                )
+            }
+            MatchEvent::Injury { team, effect } => {
+                let effect = match effect {
+                    InjuryEffect::Knock => "playing on",
+                    InjuryEffect::Substitution => "has to be substituted",
+                    InjuryEffect::Off => "off with no substitutes left",
+                };
+                format!("{} player injured, {}", team.name(), effect)
+            }
+            MatchEvent::ExtraTime => "Level at full time, extra time to be played".to_string(),
+            MatchEvent::FullTime => "Full time".to_string(),
+            MatchEvent::ShootoutKick { team, scored } => {
+                let result = if scored { "scored" } else { "missed" };
+                format!("Penalty {} by {}", result, team.name())
+            }
+            MatchEvent::ShootoutWon { team } => format!("{} win on penalties", team.name()),
+        }
+    }
+}
+
+/// Players who have been in a physical contest since injuries were last
+/// checked for, and whether they were fouled.
+#[derive(Default)]
+pub struct Challenges {
+    pub players: Vec<(Entity, bool)>,
+}
+
+/// Every tile a runner stepped onto this frame, in the order they got there,
+/// so that nothing they passed through on the way is missed however long
+/// the frame was.
+#[derive(Default)]
+pub struct Steps {
+    pub taken: Vec<(Entity, Point2<u32>)>,
+    /// Each runner of the activation, their team and every tile they have
+    /// been on in it, starting where they set off, sorted by that tile.
+    pub trails: Vec<(Entity, PlayerTeam, Vec<Point2<u32>>)>,
+    /// How many steps along the trails have been checked for runners
+    /// meeting, and the pairs of them who already have.
+    pub checked: usize,
+    pub met: Vec<(Entity, Entity)>,
+}
+
+/// Everything that has happened in the match so far, oldest first.
+#[derive(Default)]
+pub struct MatchEvents {
+    pub events: Vec<MatchEvent>,
+}
+
+/// How far through the match it is, counted in actions played out.
+pub struct MatchClock {
+    pub half: u32,
+    pub elapsed: u32,
+}
+
+impl MatchClock {
+    pub fn new() -> Self {
+        Self {
+            half: 1,
+            elapsed: 0,
+        }
+    }
+
+    pub fn in_extra_time(&self) -> bool {
+        self.half > 2
+    }
+
+    /// Red kick off the first half and Blue the second, and the same in
+    /// extra time.
+    pub fn kick_off_team(&self) -> PlayerTeam {
+        if self.half % 2 == 1 {
+            PlayerTeam::Red
+        } else {
+            PlayerTeam::Blue
+        }
+    }
+}
+
+/// The kicks each team has taken in a penalty shootout, and whether they
+/// went in.
+#[derive(Default)]
+pub struct Shootout {
+    pub started: bool,
+    pub red: Vec<bool>,
+    pub blue: Vec<bool>,
+    /// Everyone who has taken a kick since their team last ran out of
+    /// players who hadn't.
+    pub taken: Vec<Entity>,
+    /// Where the goalkeeper facing the next kick dives. Only the side who
+    /// chose it knows until the kick has been aimed, and their opponent
+    /// holds them to it with the `commitment` made from it and the `salt`.
+    pub dive: Option<Point2<u32>>,
+    pub salt: u64,
+    pub commitment: Option<u64>,
+}
+
+impl Shootout {
+    pub fn kicks(&self, team: PlayerTeam) -> &[bool] {
+        match team {
+            PlayerTeam::Red => &self.red,
+            PlayerTeam::Blue => &self.blue,
+        }
+    }
+
+    pub fn scored(&self, team: PlayerTeam) -> usize {
+        self.kicks(team).iter().filter(|&&scored| scored).count()
+    }
+
+    pub fn record(&mut self, team: PlayerTeam, scored: bool) {
+        match team {
+            PlayerTeam::Red => self.red.push(scored),
+            PlayerTeam::Blue => self.blue.push(scored),
+        }
+    }
+
+    /// Red go first, and then the teams take turns.
+    pub fn next_team(&self) -> PlayerTeam {
+        if self.red.len() <= self.blue.len() {
+            PlayerTeam::Red
+        } else {
+            PlayerTeam::Blue
+        }
+    }
+
+    /// The winner once one team is out of the other's reach within `kicks`
+    /// each, or after that once one has scored more from the same number.
+    pub fn winner(&self, kicks: usize) -> Option<PlayerTeam> {
+        let (red, blue) = (self.scored(PlayerTeam::Red), self.scored(PlayerTeam::Blue));
+        let (red_taken, blue_taken) = (self.red.len(), self.blue.len());
+        if red_taken <= kicks && blue_taken <= kicks {
+            if red > blue + (kicks - blue_taken) {
+                return Some(PlayerTeam::Red);
+            }
+            if blue > red + (kicks - red_taken) {
+                return Some(PlayerTeam::Blue);
+            }
+        }
+        if red_taken == blue_taken && red_taken >= kicks && red != blue {
+            return Some(if red > blue {
+                PlayerTeam::Red
+            } else {
+                PlayerTeam::Blue
+            });
+        }
+        None
+    }
+}
+
+/// How many substitutions each team has made.
+#[derive(Default)]
+pub struct Substitutions {
+    pub red: u32,
+    pub blue: u32,
+}
+
+impl Substitutions {
+    pub fn made(&self, team: PlayerTeam) -> u32 {
+        match team {
+            PlayerTeam::Red => self.red,
+            PlayerTeam::Blue => self.blue,
+        }
+    }
+
+    pub fn add(&mut self, team: PlayerTeam) {
+        match team {
+            PlayerTeam::Red => self.red += 1,
+            PlayerTeam::Blue => self.blue += 1,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Score {
+    pub red: u32,
+    pub blue: u32,
+}
+
+impl Score {
+    pub fn add_goal(&mut self, team: PlayerTeam) {
+        match team {
+            PlayerTeam::Red => self.red += 1,
+            PlayerTeam::Blue => self.blue += 1,
+        }
+    }
+}
+
+pub struct TurnLog {
+    /// Hash of the game state before the first action of this turn.
+    pub start_hash: u64,
+    pub actions: Vec<TurnAction>,
+    /// Imported actions still waiting to be played out.
+    pub replay: VecDeque<TurnAction>,
+    pub replaying: bool,
+}
+
+impl TurnLog {
+    pub fn new(start_hash: u64) -> Self {
+        Self {
+            start_hash,
+            actions: Vec::new(),
+            replay: VecDeque::new(),
+            replaying: false,
+        }
+    }
+
+    pub fn reset(&mut self, start_hash: u64) {
+        self.start_hash = start_hash;
+        self.actions.clear();
+    }
+}
+
+/// How long a notice stays on screen, in seconds.
+const NOTICE_TIME: f32 = 5.0;
+
+/// A message for the players about something outside the match itself,
+/// such as a turn file which couldn't be loaded.
+#[derive(Default)]
+pub struct Notice {
+    pub text: Option<String>,
+    pub remaining: f32,
+}
+
+impl Notice {
+    pub fn show(&mut self, text: String) {
+        self.text = Some(text);
+        self.remaining = NOTICE_TIME;
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.remaining -= dt;
+        if self.remaining <= 0.0 {
+            self.text = None;
+        }
+    }
+}
+
+/// A small seeded random number generator (xorshift64*). Every client
+/// starts a match from the same seed and draws from it in the same order,
+/// so replayed turns have the same outcomes.
+pub struct Rng {
+    pub state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        // The generator gets stuck on zero
+        let state = if seed == 0 {
+            0x9e37_79b9_7f4a_7c15
+        } else {
+            seed
+        };
+        Self { state }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let mut x = self.state;
+        x ^= x >> 12;
+        x ^= x << 25;
+        x ^= x >> 27;
+        self.state = x;
+        x.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// A number from 0 up to but not including 1.
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// A number from `min` to `max` inclusive.
+    pub fn range(&mut self, min: i32, max: i32) -> i32 {
+        min + (self.next_u64() % (max - min + 1) as u64) as i32
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
 }
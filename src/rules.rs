@@ -0,0 +1,367 @@
+//! Tunable game constants, loaded from a rules file so that they can be
+//! changed without recompiling.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use config::{self, ConfigError, Entry};
+
+pub struct Rules {
+    /// Speeds are in tiles per second.
+    pub cursor_speed: f32,
+    pub camera_speed: f32,
+    pub player_speed: f32,
+    pub pass_speed: f32,
+    /// Distances are in tiles.
+    pub player_move_distance: u32,
+    pub ball_pass_distance: u32,
+    pub lofted_pass_distance: u32,
+    /// How far a player can run with the ball at their feet, and how far
+    /// they can sprint after knocking it on ahead of them.
+    pub dribble_move_distance: u32,
+    pub knock_on_distance: u32,
+    /// Chance of each opponent an average dribbler runs next to winning the
+    /// ball off them.
+    pub dribble_tackle_chance: f32,
+    /// Tiles a lofted pass may stray per tile travelled, for the worst passer.
+    pub lofted_pass_scatter: f32,
+    /// Chance of each opponent next to where a lofted pass lands winning it.
+    pub lofted_pass_intercept_chance: f32,
+    /// Chance of an average receiver controlling the gentlest pass.
+    pub first_touch_chance: f32,
+    /// How much less likely the hardest pass is to be controlled.
+    pub first_touch_power_penalty: f32,
+    /// Speed of a loose ball from the hardest pass or deflection.
+    pub roll_speed: f32,
+    /// In tiles per second per second.
+    pub roll_deceleration: f32,
+    /// How far from the goal a player can shoot, in tiles, and how quickly
+    /// shots travel.
+    pub shot_range: u32,
+    pub shot_speed: f32,
+    /// Tiles a shot may stray along the goal line per tile from the goal,
+    /// for the worst shooter.
+    pub shot_scatter: f32,
+    /// Further tiles a shot may stray for each opponent closing the shooter
+    /// down.
+    pub shot_pressure_scatter: f32,
+    /// Chance of each player in the way blocking a shot.
+    pub shot_block_chance: f32,
+    /// How many tiles a goalkeeper can dive across, and the chance of an
+    /// average one stopping a gentle ball right in front of them.
+    pub keeper_reach: u32,
+    pub keeper_save_chance: f32,
+    /// How much less likely the hardest strike is to be saved.
+    pub keeper_power_penalty: f32,
+    /// How many actions each half lasts.
+    pub half_length: u32,
+    /// Whether a match level at full time goes to extra time, if each half
+    /// of it lasts any actions, and then penalties, with at least
+    /// `shootout_kicks` for each team before sudden death.
+    pub knockout: bool,
+    pub extra_time_length: u32,
+    pub shootout_kicks: u32,
+    /// How many substitutions each team may make.
+    pub max_substitutions: u32,
+    /// How far opponents have to stand from the ball at a restart, which is
+    /// where the wall lines up for a free kick.
+    pub restart_distance: u32,
+    /// Whether passes to players in an offside position are penalised.
+    pub offside: bool,
+    /// Chance of a challenge on the ball carrier being a foul, depending on
+    /// whether it comes from in front of them, the side or behind.
+    pub foul_chance_front: f32,
+    pub foul_chance_side: f32,
+    pub foul_chance_behind: f32,
+    /// Chance of a foul getting a yellow card, or a red one.
+    pub yellow_card_chance: f32,
+    pub red_card_chance: f32,
+    /// Chance of a player in a challenge or collision getting hurt, or of a
+    /// fouled player getting hurt.
+    pub injury_chance: f32,
+    pub foul_injury_chance: f32,
+    /// Chance of an injury being serious enough to force the player off.
+    pub serious_injury_chance: f32,
+    /// How much a knock takes off each of a player's attributes.
+    pub injury_attribute_loss: u32,
+    /// Whether players may run diagonally, and what each diagonal step costs
+    /// out of their move distance.
+    pub diagonal_movement: bool,
+    pub diagonal_cost: f32,
+    /// What stepping next to an opponent costs on top of the step itself,
+    /// and whether it ends the run there.
+    pub zone_of_control_cost: f32,
+    pub zone_of_control_stops: bool,
+    /// Whether to shade opponents' zones of control while choosing a run.
+    pub show_zones_of_control: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            cursor_speed: 5.0,
+            camera_speed: 10.0,
+            player_speed: 10.0,
+            pass_speed: 15.0,
+            player_move_distance: 4,
+            ball_pass_distance: 8,
+            lofted_pass_distance: 12,
+            dribble_move_distance: 3,
+            knock_on_distance: 6,
+            dribble_tackle_chance: 0.3,
+            lofted_pass_scatter: 0.25,
+            lofted_pass_intercept_chance: 0.5,
+            first_touch_chance: 0.9,
+            first_touch_power_penalty: 0.4,
+            roll_speed: 10.0,
+            roll_deceleration: 10.0,
+            shot_range: 16,
+            shot_speed: 25.0,
+            shot_scatter: 0.1,
+            shot_pressure_scatter: 0.5,
+            shot_block_chance: 0.5,
+            keeper_reach: 2,
+            keeper_save_chance: 0.7,
+            keeper_power_penalty: 0.4,
+            half_length: 40,
+            knockout: false,
+            extra_time_length: 10,
+            shootout_kicks: 5,
+            max_substitutions: 3,
+            restart_distance: 4,
+            offside: true,
+            foul_chance_front: 0.05,
+            foul_chance_side: 0.15,
+            foul_chance_behind: 0.4,
+            yellow_card_chance: 0.3,
+            red_card_chance: 0.05,
+            injury_chance: 0.02,
+            foul_injury_chance: 0.15,
+            serious_injury_chance: 0.25,
+            injury_attribute_loss: 2,
+            diagonal_movement: false,
+            diagonal_cost: 1.5,
+            zone_of_control_cost: 1.0,
+            zone_of_control_stops: false,
+            show_zones_of_control: true,
+        }
+    }
+}
+
+fn parse_speed(entry: &Entry) -> Result<f32, ConfigError> {
+    match entry.value.parse::<f32>() {
+        Ok(speed) if speed > 0.0 && speed.is_finite() => Ok(speed),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+fn parse_distance(entry: &Entry) -> Result<u32, ConfigError> {
+    match entry.value.parse::<u32>() {
+        Ok(distance) if distance > 0 => Ok(distance),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+fn parse_count(entry: &Entry) -> Result<u32, ConfigError> {
+    entry
+        .value
+        .parse::<u32>()
+        .map_err(|_| ConfigError::invalid_value(entry))
+}
+
+fn parse_factor(entry: &Entry) -> Result<f32, ConfigError> {
+    match entry.value.parse::<f32>() {
+        Ok(factor) if factor >= 0.0 && factor.is_finite() => Ok(factor),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+fn parse_chance(entry: &Entry) -> Result<f32, ConfigError> {
+    match entry.value.parse::<f32>() {
+        Ok(chance) if chance >= 0.0 && chance <= 1.0 => Ok(chance),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+fn parse_flag(entry: &Entry) -> Result<bool, ConfigError> {
+    match entry.value {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        _ => Err(ConfigError::invalid_value(entry)),
+    }
+}
+
+impl Rules {
+    /// Parses rules, using the defaults for any which are not given.
+    pub fn parse(contents: &str) -> Result<Self, ConfigError> {
+        let mut rules = Self::default();
+
+        for entry in config::parse(contents)? {
+            match entry.name {
+                "cursor_speed" => rules.cursor_speed = parse_speed(&entry)?,
+                "camera_speed" => rules.camera_speed = parse_speed(&entry)?,
+                "player_speed" => rules.player_speed = parse_speed(&entry)?,
+                "pass_speed" => rules.pass_speed = parse_speed(&entry)?,
+                "player_move_distance" => rules.player_move_distance = parse_distance(&entry)?,
+                "ball_pass_distance" => rules.ball_pass_distance = parse_distance(&entry)?,
+                "lofted_pass_distance" => rules.lofted_pass_distance = parse_distance(&entry)?,
+                "dribble_move_distance" => rules.dribble_move_distance = parse_distance(&entry)?,
+                "knock_on_distance" => rules.knock_on_distance = parse_distance(&entry)?,
+                "dribble_tackle_chance" => rules.dribble_tackle_chance = parse_chance(&entry)?,
+                "lofted_pass_scatter" => rules.lofted_pass_scatter = parse_factor(&entry)?,
+                "lofted_pass_intercept_chance" => {
+                    rules.lofted_pass_intercept_chance = parse_chance(&entry)?
+                }
+                "first_touch_chance" => rules.first_touch_chance = parse_chance(&entry)?,
+                "first_touch_power_penalty" => {
+                    rules.first_touch_power_penalty = parse_chance(&entry)?
+                }
+                "roll_speed" => rules.roll_speed = parse_speed(&entry)?,
+                "roll_deceleration" => rules.roll_deceleration = parse_speed(&entry)?,
+                "shot_range" => rules.shot_range = parse_distance(&entry)?,
+                "shot_speed" => rules.shot_speed = parse_speed(&entry)?,
+                "shot_scatter" => rules.shot_scatter = parse_factor(&entry)?,
+                "shot_pressure_scatter" => rules.shot_pressure_scatter = parse_factor(&entry)?,
+                "shot_block_chance" => rules.shot_block_chance = parse_chance(&entry)?,
+                "keeper_reach" => rules.keeper_reach = parse_distance(&entry)?,
+                "keeper_save_chance" => rules.keeper_save_chance = parse_chance(&entry)?,
+                "keeper_power_penalty" => rules.keeper_power_penalty = parse_chance(&entry)?,
+                "half_length" => rules.half_length = parse_distance(&entry)?,
+                "knockout" => rules.knockout = parse_flag(&entry)?,
+                "extra_time_length" => rules.extra_time_length = parse_count(&entry)?,
+                "shootout_kicks" => rules.shootout_kicks = parse_distance(&entry)?,
+                "max_substitutions" => rules.max_substitutions = parse_count(&entry)?,
+                "restart_distance" => rules.restart_distance = parse_distance(&entry)?,
+                "offside" => rules.offside = parse_flag(&entry)?,
+                "foul_chance_front" => rules.foul_chance_front = parse_chance(&entry)?,
+                "foul_chance_side" => rules.foul_chance_side = parse_chance(&entry)?,
+                "foul_chance_behind" => rules.foul_chance_behind = parse_chance(&entry)?,
+                "yellow_card_chance" => rules.yellow_card_chance = parse_chance(&entry)?,
+                "red_card_chance" => rules.red_card_chance = parse_chance(&entry)?,
+                "injury_chance" => rules.injury_chance = parse_chance(&entry)?,
+                "foul_injury_chance" => rules.foul_injury_chance = parse_chance(&entry)?,
+                "serious_injury_chance" => rules.serious_injury_chance = parse_chance(&entry)?,
+                "injury_attribute_loss" => rules.injury_attribute_loss = parse_count(&entry)?,
+                "diagonal_movement" => rules.diagonal_movement = parse_flag(&entry)?,
+                "diagonal_cost" => rules.diagonal_cost = parse_speed(&entry)?,
+                "zone_of_control_cost" => rules.zone_of_control_cost = parse_factor(&entry)?,
+                "zone_of_control_stops" => rules.zone_of_control_stops = parse_flag(&entry)?,
+                "show_zones_of_control" => rules.show_zones_of_control = parse_flag(&entry)?,
+                _ => return Err(ConfigError::unknown_field(&entry)),
+            }
+        }
+
+        Ok(rules)
+    }
+
+    pub fn load(path: &Path) -> Result<Self, ConfigError> {
+        Self::parse(&config::read_file(path)?)
+    }
+
+    /// Every rule which can change what happens in a match, written out so
+    /// that two games can be checked for playing by the same rules. Those
+    /// which only change how it looks, like the cursor speed, are left out.
+    /// Speeds are in because runners and the ball race each other.
+    pub fn summary(&self) -> String {
+        let values = [
+            format!("{:?}", self.player_speed),
+            format!("{:?}", self.pass_speed),
+            format!("{}", self.player_move_distance),
+            format!("{}", self.ball_pass_distance),
+            format!("{}", self.lofted_pass_distance),
+            format!("{}", self.dribble_move_distance),
+            format!("{}", self.knock_on_distance),
+            format!("{:?}", self.dribble_tackle_chance),
+            format!("{:?}", self.lofted_pass_scatter),
+            format!("{:?}", self.lofted_pass_intercept_chance),
+            format!("{:?}", self.first_touch_chance),
+            format!("{:?}", self.first_touch_power_penalty),
+            format!("{:?}", self.roll_speed),
+            format!("{:?}", self.roll_deceleration),
+            format!("{}", self.shot_range),
+            format!("{:?}", self.shot_speed),
+            format!("{:?}", self.shot_scatter),
+            format!("{:?}", self.shot_pressure_scatter),
+            format!("{:?}", self.shot_block_chance),
+            format!("{}", self.keeper_reach),
+            format!("{:?}", self.keeper_save_chance),
+            format!("{:?}", self.keeper_power_penalty),
+            format!("{}", self.half_length),
+            format!("{}", self.knockout),
+            format!("{}", self.extra_time_length),
+            format!("{}", self.shootout_kicks),
+            format!("{}", self.max_substitutions),
+            format!("{}", self.restart_distance),
+            format!("{}", self.offside),
+            format!("{:?}", self.foul_chance_front),
+            format!("{:?}", self.foul_chance_side),
+            format!("{:?}", self.foul_chance_behind),
+            format!("{:?}", self.yellow_card_chance),
+            format!("{:?}", self.red_card_chance),
+            format!("{:?}", self.injury_chance),
+            format!("{:?}", self.foul_injury_chance),
+            format!("{:?}", self.serious_injury_chance),
+            format!("{}", self.injury_attribute_loss),
+            format!("{}", self.diagonal_movement),
+            format!("{:?}", self.diagonal_cost),
+            format!("{:?}", self.zone_of_control_cost),
+            format!("{}", self.zone_of_control_stops),
+        ];
+        values.join(",")
+    }
+
+    /// The cost of a diagonal step in a run, or `None` if runs may only go
+    /// up, down, left and right.
+    pub fn run_diagonal_cost(&self) -> Option<f32> {
+        if self.diagonal_movement {
+            Some(self.diagonal_cost)
+        } else {
+            None
+        }
+    }
+}
+
+/// Reloads the rules file whenever it changes on disk.
+pub struct RulesWatcher {
+    path: PathBuf,
+    modified: Option<SystemTime>,
+    check_timer: f32,
+}
+
+const CHECK_INTERVAL: f32 = 1.0;
+
+impl RulesWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified_time(path),
+            check_timer: CHECK_INTERVAL,
+        }
+    }
+
+    /// Returns the result of loading the file again if it has changed since
+    /// it was last checked. Broken files are handed back as errors to be
+    /// shown, so that a typo doesn't end the game.
+    pub fn poll(&mut self, dt: f32) -> Option<Result<Rules, ConfigError>> {
+        self.check_timer -= dt;
+        if self.check_timer > 0.0 {
+            return None;
+        }
+        self.check_timer = CHECK_INTERVAL;
+
+        let modified = modified_time(&self.path);
+        if modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+
+        Some(Rules::load(&self.path))
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
@@ -1,31 +1,78 @@
+use std::collections::hash_map::RandomState;
 use std::collections::HashMap;
+use std::f32::consts::PI;
+use std::hash::{BuildHasher, Hasher};
 
-use nalgebra::{Matrix4, Point2, Vector2, Vector3};
-use specs::{Entities, Fetch, FetchMut, Join, ReadStorage, System, WriteStorage};
+use nalgebra::{Point2, Vector2};
+use specs::{Entities, Entity, Fetch, FetchMut, Join, ReadStorage, System, WriteStorage};
 
 use components::{
-    Ball, BallState, CanMove, Cursor, CursorState, Direction, Player, PlayerState, SubTilePosition,
-    TilePosition,
+    sorted_bench, Attributes, Ball, BallState, Bench, CanMove, Cards, Cursor, CursorState,
+    Direction, Elevation, Formation, Injury, OffPitch, Player, PlayerState, PlayerTeam, Role,
+    StepCosts, SubTilePosition, TilePosition, MAX_ATTRIBUTE,
 };
-use resources::{Camera, DeltaTime, Input, Map, Turn, TurnState};
-
-const CURSOR_SPEED: f32 = 320.0;
-const CAMERA_SPEED: f32 = 640.0;
-const PLAYER_SPEED: f32 = 640.0;
-const PASS_SPEED: f32 = 960.0;
-const TILE_SIZE: u32 = 64;
-const PLAYER_MOVE_DISTANCE: u32 = 4;
-const BALL_PASS_DISTANCE: u32 = 8;
-
-fn tile_to_subtile(tile_pos: Point2<u32>) -> Point2<f32> {
-    Point2::new(
-        (tile_pos.x * TILE_SIZE) as f32,
-        (tile_pos.y * TILE_SIZE) as f32,
-    )
+use conditions::Conditions;
+use resources::{
+    Action, ActionMenu, Camera, Card, Challenges, DeltaTime, InjuryEffect, Input, Map, MatchClock,
+    MatchEvent, MatchEvents, MenuAction, Notice, PendingRestart, Restart, RestartKind, Rng, Score,
+    Shootout, Steps, Substitutions, Turn, TurnAction, TurnLog, TurnState,
+};
+use rules::Rules;
+use turn_file;
+
+// How quickly the camera catches up with moving players and balls
+const CAMERA_FOLLOW_RATE: f32 = 4.0;
+// Distance the cursor may come to the edge of the screen before the view moves
+const CAMERA_DEAD_ZONE: f32 = 192.0;
+// Zoom factor per notch of the mouse wheel
+const ZOOM_STEP: f32 = 1.25;
+// Zoom factor per second while a zoom key is held
+const ZOOM_RATE: f32 = 3.0;
+const STICK_DEAD_ZONE: f32 = 0.3;
+// Sine of 22.5 degrees, splitting stick angles into eight equal sectors
+const STICK_DIAGONAL_THRESHOLD: f32 = 0.383;
+const CURSOR_REPEAT_DELAY: f32 = 0.4;
+// Peak height of a lofted pass, in tiles per tile travelled
+const LOFTED_PASS_HEIGHT: f32 = 0.25;
+// Rolling balls are drawn slowing to this speed, in tiles per second, rather
+// than stopping dead between tiles
+const MIN_ROLL_SPEED: f32 = 1.0;
+// Even the softest pass bounces off a player who fails to control it
+const MIN_DEFLECTION_POWER: f32 = 0.4;
+// How hard a won tackle knocks the ball away, as for a pass
+const TACKLE_POWER: f32 = 0.7;
+
+/// Converts a speed in tiles per second along `disp` into a velocity in
+/// pixels, which differs by axis if tiles aren't square.
+fn velocity_towards(disp: Vector2<f32>, tile_size: Vector2<f32>, speed: f32) -> Vector2<f32> {
+    disp.component_div(&tile_size)
+        .normalize()
+        .component_mul(&tile_size)
+        * speed
+}
+
+fn apply_dead_zone(stick: Vector2<f32>) -> Vector2<f32> {
+    if stick.norm() < STICK_DEAD_ZONE {
+        Vector2::new(0.0, 0.0)
+    } else {
+        stick
+    }
 }
 
 fn get_input(input: &Input) -> Option<Direction> {
-    match (input.left, input.up, input.right, input.down) {
+    let stick = apply_dead_zone(input.stick);
+    let threshold = stick.norm() * STICK_DIAGONAL_THRESHOLD;
+    let stick_left = stick.x < 0.0 && stick.x.abs() > threshold;
+    let stick_right = stick.x > 0.0 && stick.x.abs() > threshold;
+    let stick_up = stick.y > 0.0 && stick.y.abs() > threshold;
+    let stick_down = stick.y < 0.0 && stick.y.abs() > threshold;
+
+    match (
+        input.is_held(Action::CursorLeft) || stick_left,
+        input.is_held(Action::CursorUp) || stick_up,
+        input.is_held(Action::CursorRight) || stick_right,
+        input.is_held(Action::CursorDown) || stick_down,
+    ) {
         (true, false, false, false) => Some(Direction::Left),
         (true, true, false, false) => Some(Direction::UpLeft),
         (false, true, false, false) => Some(Direction::Up),
@@ -72,27 +119,109 @@ fn required_time(displacement: f32, velocity: f32) -> f32 {
     }
 }
 
+/// Pans the camera by input, follows the cursor and anything moving, and
+/// keeps the view on the pitch.
 pub struct CameraSystem;
 
 impl<'a> System<'a> for CameraSystem {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
-    type SystemData = (FetchMut<'a, Camera>, Fetch<'a, DeltaTime>, Fetch<'a, Input>);
+    type SystemData = (
+        FetchMut<'a, Camera>,
+        Fetch<'a, DeltaTime>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, Turn>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, SubTilePosition>,
+    );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (mut camera, dt, input) = data;
+        let (mut camera, dt, input, map, rules, turn, cursors, sub_tile_positions) = data;
+
+        let mut pan = Vector2::new(0.0, 0.0);
+        if input.is_held(Action::CameraLeft) {
+            pan.x -= 1.0;
+        }
+        if input.is_held(Action::CameraUp) {
+            pan.y -= 1.0;
+        }
+        if input.is_held(Action::CameraRight) {
+            pan.x += 1.0;
+        }
+        if input.is_held(Action::CameraDown) {
+            pan.y += 1.0;
+        }
+        let stick = apply_dead_zone(input.camera_stick);
+        pan += Vector2::new(stick.x, -stick.y);
+        // Pan at a constant speed on screen regardless of zoom
+        let tile_size = map.tile_size();
+        camera.pos += pan.component_mul(&tile_size) * rules.camera_speed / camera.zoom * dt.dt;
 
-        if input.a {
-            camera.mat *= Matrix4::new_translation(&Vector3::new(CAMERA_SPEED * dt.dt, 0.0, 0.0));
+        let tile_centre = tile_size / 2.0;
+
+        // Zoom around the cursor
+        let anchor = (&cursors, &sub_tile_positions)
+            .join()
+            .map(|(_, pos)| pos.pos + tile_centre)
+            .next()
+            .unwrap_or_else(|| {
+                camera.screen_to_world(Point2::from_coordinates(camera.viewport / 2.0))
+            });
+        let mut zoom = camera.zoom * ZOOM_STEP.powf(input.wheel);
+        if input.is_held(Action::ZoomIn) {
+            zoom *= ZOOM_RATE.powf(dt.dt);
         }
-        if input.w {
-            camera.mat *= Matrix4::new_translation(&Vector3::new(0.0, CAMERA_SPEED * dt.dt, 0.0));
+        if input.is_held(Action::ZoomOut) {
+            zoom /= ZOOM_RATE.powf(dt.dt);
         }
-        if input.d {
-            camera.mat *= Matrix4::new_translation(&Vector3::new(-CAMERA_SPEED * dt.dt, 0.0, 0.0));
+        if zoom != camera.zoom {
+            camera.unfitted_zoom = None;
+            camera.zoom_around(zoom, anchor);
         }
-        if input.s {
-            camera.mat *= Matrix4::new_translation(&Vector3::new(0.0, -CAMERA_SPEED * dt.dt, 0.0));
+        if input.was_pressed(Action::FitPitch) {
+            camera.toggle_fit(anchor);
+        }
+        let tracked = match turn.state {
+            TurnState::Running { player_id } => sub_tile_positions.get(player_id),
+            TurnState::Passing { ball_id, .. } | TurnState::ThroughBall { ball_id, .. } => {
+                sub_tile_positions.get(ball_id)
+            }
+            _ => None,
+        };
+
+        if let Some(tracked) = tracked {
+            // Smoothly track whatever is moving
+            let target = camera.pos_centred_on(tracked.pos + tile_centre);
+            let t = (CAMERA_FOLLOW_RATE * dt.dt).min(1.0);
+            let offset = (target - camera.pos) * t;
+            camera.pos += offset;
+        } else if !input.mouse_active {
+            // Push the view along when the cursor moves outside the dead zone
+            for (cursor, pos) in (&cursors, &sub_tile_positions).join() {
+                if cursor.state == CursorState::Still {
+                    continue;
+                }
+
+                let dead_zone = CAMERA_DEAD_ZONE / camera.zoom;
+                let min = camera.pos + Vector2::new(dead_zone, dead_zone);
+                let max = camera.pos + camera.view_size()
+                    - Vector2::new(dead_zone, dead_zone)
+                    - tile_size;
+                if pos.pos.x < min.x {
+                    camera.pos.x -= min.x - pos.pos.x;
+                } else if pos.pos.x > max.x {
+                    camera.pos.x += pos.pos.x - max.x;
+                }
+                if pos.pos.y < min.y {
+                    camera.pos.y -= min.y - pos.pos.y;
+                } else if pos.pos.y > max.y {
+                    camera.pos.y += pos.pos.y - max.y;
+                }
+            }
         }
+
+        camera.clamp(map.pixel_size());
     }
 }
 
@@ -103,36 +232,61 @@ impl<'a> System<'a> for CursorMovementSystem {
     type SystemData = (
         Fetch<'a, DeltaTime>,
         Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
         WriteStorage<'a, Cursor>,
         WriteStorage<'a, TilePosition>,
         WriteStorage<'a, SubTilePosition>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (dt, input, mut cursors, mut tile_positions, mut sub_tile_positions) = data;
+        let (dt, input, map, rules, mut cursors, mut tile_positions, mut sub_tile_positions) = data;
 
         for (cursor, tile_position, sub_tile_position) in
             (&mut cursors, &mut tile_positions, &mut sub_tile_positions).join()
         {
             let mut remaining_dt = dt.dt;
+            cursor.repeat_delay = (cursor.repeat_delay - dt.dt).max(0.0);
 
             while remaining_dt > 0.0 {
                 if cursor.state == CursorState::Still {
                     // If we're still then the subtile position must be equal to the tile position
-                    assert!(tile_to_subtile(tile_position.pos) == sub_tile_position.pos);
+                    assert!(map.tile_to_world(tile_position.pos) == sub_tile_position.pos);
+
+                    let direction = get_input(&input);
+                    if direction.is_none() {
+                        cursor.held = false;
+                        cursor.repeat_delay = 0.0;
+                    }
+                    if cursor.repeat_delay > 0.0 {
+                        // Still waiting to repeat the previous movement
+                        break;
+                    }
+
+                    if let Some(direction) = direction {
+                        // Pause briefly after the first step (timed from when it
+                        // starts) so that single tiles can be picked, then move
+                        // continuously.
+                        if !cursor.held {
+                            cursor.repeat_delay = CURSOR_REPEAT_DELAY;
+                        }
+                        cursor.held = true;
 
-                    if let Some(direction) = get_input(&input) {
-                        let velocity =
-                            vector_from_direction_f32(&direction).normalize() * CURSOR_SPEED;
+                        let velocity = velocity_towards(
+                            vector_from_direction_f32(&direction),
+                            map.tile_size(),
+                            rules.cursor_speed,
+                        );
                         let offset = vector_from_direction_i32(&direction);
-                        // Can't move left or above 0, 0
-                        if tile_position.pos.x as i32 + offset.x >= 0
-                            && tile_position.pos.y as i32 + offset.y >= 0
+                        // Can't move off the edge of the map
+                        let target_x = tile_position.pos.x as i32 + offset.x;
+                        let target_y = tile_position.pos.y as i32 + offset.y;
+                        if target_x >= 0
+                            && target_y >= 0
+                            && target_x < map.map.width as i32
+                            && target_y < map.map.height as i32
                         {
-                            let target = Point2::new(
-                                (tile_position.pos.x as i32 + offset.x) as u32,
-                                (tile_position.pos.y as i32 + offset.y) as u32,
-                            );
+                            let target = Point2::new(target_x as u32, target_y as u32);
 
                             cursor.state = CursorState::Moving { velocity, target };
                         }
@@ -145,7 +299,7 @@ impl<'a> System<'a> for CursorMovementSystem {
                 }
 
                 if let CursorState::Moving { velocity, target } = cursor.state {
-                    let disp = tile_to_subtile(target) - sub_tile_position.pos;
+                    let disp = map.tile_to_world(target) - sub_tile_position.pos;
                     let required_dt_x = required_time(disp.x, velocity.x);
                     let required_dt_y = required_time(disp.y, velocity.y);
 
@@ -156,7 +310,7 @@ impl<'a> System<'a> for CursorMovementSystem {
                         Vector2::new(velocity.x * remaining_dt_x, velocity.y * remaining_dt_y);
                     remaining_dt -= remaining_dt_x.max(remaining_dt_y);
 
-                    if sub_tile_position.pos == tile_to_subtile(target) {
+                    if sub_tile_position.pos == map.tile_to_world(target) {
                         tile_position.pos = target;
                         cursor.state = CursorState::Still;
                     }
@@ -166,6 +320,133 @@ impl<'a> System<'a> for CursorMovementSystem {
     }
 }
 
+/// Moves the cursor towards the mouse and turns clicks into selections.
+pub struct MouseSystem;
+
+impl<'a> System<'a> for MouseSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Fetch<'a, Camera>,
+        Fetch<'a, Map>,
+        Fetch<'a, Turn>,
+        FetchMut<'a, Input>,
+        FetchMut<'a, ActionMenu>,
+        ReadStorage<'a, CanMove>,
+        WriteStorage<'a, Cursor>,
+        WriteStorage<'a, TilePosition>,
+        WriteStorage<'a, SubTilePosition>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            camera,
+            map,
+            turn,
+            mut input,
+            mut menu,
+            can_moves,
+            mut cursors,
+            mut tile_positions,
+            mut sub_tile_positions,
+        ) = data;
+
+        if !input.mouse_active {
+            return;
+        }
+
+        match turn.state {
+            TurnState::ActionMenu { .. } => {
+                if let Some(index) = menu.item_at(input.mouse_pos) {
+                    menu.selected = index;
+                    if input.mouse_clicked {
+                        input.trigger(Action::Select);
+                    }
+                }
+                input.mouse_clicked = false;
+            }
+            TurnState::SelectPlayer
+            | TurnState::SelectRun { .. }
+            | TurnState::SelectPass { .. }
+            | TurnState::SelectLoftedPass { .. }
+            | TurnState::SelectThroughBall { .. }
+            | TurnState::SelectReceiver { .. }
+            | TurnState::SelectKnockOn { .. }
+            | TurnState::SelectShot { .. }
+            | TurnState::ShootoutTaker { .. }
+            | TurnState::ShootoutDive { .. }
+            | TurnState::ShootoutShot { .. } => {
+                let mouse_tile = match map.world_to_tile(camera.screen_to_world(input.mouse_pos)) {
+                    Some(mouse_tile) => mouse_tile,
+                    None => {
+                        input.mouse_clicked = false;
+                        return;
+                    }
+                };
+
+                // Paths are only traced while dragging, or on the way to a
+                // click, and can only take diagonal steps if the path can.
+                let tracing = match turn.state {
+                    TurnState::SelectRun { .. }
+                    | TurnState::SelectPass { .. }
+                    | TurnState::SelectThroughBall { .. }
+                    | TurnState::SelectKnockOn { .. } => true,
+                    _ => false,
+                };
+                let diagonal = (&can_moves,)
+                    .join()
+                    .any(|(can_move,)| can_move.costs.diagonal.is_some());
+
+                for (cursor, tile_position, sub_tile_position) in
+                    (&mut cursors, &mut tile_positions, &mut sub_tile_positions).join()
+                {
+                    if cursor.state != CursorState::Still {
+                        continue;
+                    }
+
+                    let pos = tile_position.pos;
+                    let next = if !tracing {
+                        mouse_tile
+                    } else if input.mouse_dragging || input.mouse_clicked {
+                        // Step a single tile per frame, as the keyboard does,
+                        // so that the path stays contiguous.
+                        let step_x = if pos.x < mouse_tile.x {
+                            pos.x + 1
+                        } else if pos.x > mouse_tile.x {
+                            pos.x - 1
+                        } else {
+                            pos.x
+                        };
+                        let step_y = if pos.y < mouse_tile.y {
+                            pos.y + 1
+                        } else if pos.y > mouse_tile.y {
+                            pos.y - 1
+                        } else {
+                            pos.y
+                        };
+                        if diagonal || step_x == pos.x {
+                            Point2::new(step_x, step_y)
+                        } else {
+                            Point2::new(step_x, pos.y)
+                        }
+                    } else {
+                        pos
+                    };
+                    tile_position.pos = next;
+                    sub_tile_position.pos = map.tile_to_world(next);
+
+                    if next == mouse_tile && input.mouse_clicked {
+                        input.trigger(Action::Select);
+                        input.mouse_clicked = false;
+                    }
+                }
+            }
+            _ => {
+                input.mouse_clicked = false;
+            }
+        }
+    }
+}
+
 pub struct PlayerSelectSystem;
 
 impl<'a> System<'a> for PlayerSelectSystem {
@@ -183,7 +464,7 @@ impl<'a> System<'a> for PlayerSelectSystem {
         let (entities, input, mut turn, cursors, tile_positions, players) = data;
 
         for (_, cursor_pos) in (&cursors, &tile_positions).join() {
-            if input.select {
+            if input.was_pressed(Action::Select) {
                 for (player_id, _, player_pos) in (&*entities, &players, &tile_positions).join() {
                     if player_pos.pos == cursor_pos.pos {
                         turn.state = TurnState::ActionMenu { player_id };
@@ -213,10 +494,117 @@ fn get_adjacent_tiles(tile_pos: Point2<u32>, map_size: Vector2<u32>) -> Vec<Poin
     tiles
 }
 
+/// The tiles a run may step to from `tile_pos`: the adjacent ones, and the
+/// diagonal ones too if `diagonal` is set.
+fn get_step_tiles(
+    tile_pos: Point2<u32>,
+    map_size: Vector2<u32>,
+    diagonal: bool,
+) -> Vec<Point2<u32>> {
+    let mut tiles = get_adjacent_tiles(tile_pos, map_size);
+    if diagonal {
+        let left = tile_pos.x >= 1;
+        let right = tile_pos.x < map_size.x - 1;
+        let up = tile_pos.y >= 1;
+        let down = tile_pos.y < map_size.y - 1;
+        if left && up {
+            tiles.push(Point2::new(tile_pos.x - 1, tile_pos.y - 1));
+        }
+        if right && up {
+            tiles.push(Point2::new(tile_pos.x + 1, tile_pos.y - 1));
+        }
+        if left && down {
+            tiles.push(Point2::new(tile_pos.x - 1, tile_pos.y + 1));
+        }
+        if right && down {
+            tiles.push(Point2::new(tile_pos.x + 1, tile_pos.y + 1));
+        }
+    }
+
+    tiles
+}
+
+/// The tiles a path with `costs` may step to from `tile_pos`. A diagonal
+/// step can't squeeze between players on both tiles either side of it.
+fn path_step_tiles(
+    tile_pos: Point2<u32>,
+    map_size: Vector2<u32>,
+    costs: &StepCosts,
+) -> Vec<Point2<u32>> {
+    get_step_tiles(tile_pos, map_size, costs.diagonal.is_some())
+        .into_iter()
+        .filter(|&next| {
+            next.x == tile_pos.x
+                || next.y == tile_pos.y
+                || !costs.occupied.contains(&Point2::new(next.x, tile_pos.y))
+                || !costs.occupied.contains(&Point2::new(tile_pos.x, next.y))
+        })
+        .collect()
+}
+
+/// The cost of stepping between two neighbouring tiles.
+fn step_cost(from: Point2<u32>, to: Point2<u32>, costs: &StepCosts) -> f32 {
+    let cost = match costs.diagonal {
+        Some(cost) if from.x != to.x && from.y != to.y => cost,
+        _ => 1.0,
+    };
+    if costs.zones.contains(&to) {
+        cost + costs.zone_cost
+    } else {
+        cost
+    }
+}
+
+fn path_cost(start: Point2<u32>, path: &[Point2<u32>], costs: &StepCosts) -> f32 {
+    let mut cost = 0.0;
+    let mut from = start;
+    for step in path {
+        cost += step_cost(from, *step, costs);
+        from = *step;
+    }
+    cost
+}
+
+/// Whether a path which has reached `tile` has to stop there. Players can
+/// always leave a zone of control they start in.
+fn must_stop(tile: Point2<u32>, start: Point2<u32>, costs: &StepCosts) -> bool {
+    costs.zone_stops && tile != start && costs.zones.contains(&tile)
+}
+
+/// The step costs of a run by a player on `team`, hemmed in by the zones of
+/// control around every opponent.
+fn run_step_costs(
+    team: PlayerTeam,
+    map: &Map,
+    rules: &Rules,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> StepCosts {
+    let mut zones = Vec::new();
+    for &(pos, _, other_team) in player_tiles {
+        if other_team == team {
+            continue;
+        }
+        for tile in get_step_tiles(pos, map.size(), true) {
+            if !zones.contains(&tile) {
+                zones.push(tile);
+            }
+        }
+    }
+
+    StepCosts {
+        diagonal: rules.run_diagonal_cost(),
+        zones,
+        zone_cost: rules.zone_of_control_cost,
+        zone_stops: rules.zone_of_control_stops,
+        occupied: player_tiles.iter().map(|&(pos, _, _)| pos).collect(),
+    }
+}
+
 fn calculate_run_targets<'a>(
     start_pos: Point2<u32>,
     map: &Map,
     max_distance: u32,
+    costs: &StepCosts,
     players: &ReadStorage<'a, Player>,
     tile_positions: &ReadStorage<'a, TilePosition>,
 ) -> Vec<Point2<u32>> {
@@ -224,75 +612,266 @@ fn calculate_run_targets<'a>(
     let mut targets: Vec<Point2<u32>> = Vec::new();
     targets.push(start_pos);
 
-    // tiles that still need to be searched. start with those adjacent to the start.
-    let mut to_search: Vec<Point2<u32>> =
-        get_adjacent_tiles(start_pos, Vector2::new(map.map.width, map.map.height));
+    // tiles that still need to be searched from. start with the start.
+    let mut to_search: Vec<Point2<u32>> = vec![start_pos];
 
-    // tiles that are just about to be or have already been searched,
-    // and their cost for the path to the tile (but not including itself).
-    // if the same tile is encountered with a lower cost then it should be searched again.
-    let mut searched: HashMap<Point2<u32>, u32> = HashMap::new();
+    // the cheapest cost found so far for the path to each tile (including
+    // itself). if the same tile is reached more cheaply then it should be
+    // searched again.
+    let mut searched: HashMap<Point2<u32>, f32> = HashMap::new();
+    searched.insert(start_pos, 0.0);
 
-    // set the cost for the first tiles to 0
-    searched.insert(start_pos, 0);
-    for tile in &to_search {
-        searched.insert(*tile, 0);
-    }
+    while let Some(tile) = to_search.pop() {
+        // runs can end in a zone of control but not carry on through it
+        if must_stop(tile, start_pos, costs) {
+            continue;
+        }
 
-    while let Some(next) = to_search.pop() {
-        let new_distance = searched[&next] + 1; // TODO change 1 to tile cost
+        for next in path_step_tiles(tile, map.size(), costs) {
+            let new_distance = searched[&tile] + step_cost(tile, next, costs);
 
-        // if the distance is too far stop searching.
-        // but don't rule it out if we find this tile through a shorter path.
-        if new_distance > max_distance {
-            continue;
+            // if the distance is too far don't go this way.
+            // but don't rule it out if we find this tile through a shorter path.
+            if new_distance > max_distance as f32 {
+                continue;
+            }
+
+            // if the tile is already occupied or off the pitch then rule it
+            // out
+            let occupied = (players, tile_positions)
+                .join()
+                .any(|(_, pos)| pos.pos == next);
+            if occupied || !map.in_field(next) {
+                continue;
+            }
+
+            let cheaper = searched
+                .get(&next)
+                .map_or(true, |&distance| new_distance < distance);
+            if cheaper {
+                // tile looks good
+                if !targets.contains(&next) {
+                    targets.push(next);
+                }
+                searched.insert(next, new_distance);
+                to_search.push(next);
+            }
         }
+    }
 
-        // if the tile is already occupied then rule it out
-        let occupied = (players, tile_positions)
-            .join()
-            .any(|(_, pos)| pos.pos == next);
-        if occupied && next != start_pos {
+    targets
+}
+
+/// The cheapest run from `start` to `target` around other players, if there
+/// is one within a player's move distance.
+fn find_run_path(
+    start: Point2<u32>,
+    target: Point2<u32>,
+    map: &Map,
+    rules: &Rules,
+    step_costs: &StepCosts,
+    occupied: &[Point2<u32>],
+) -> Option<Vec<Point2<u32>>> {
+    let mut costs: HashMap<Point2<u32>, f32> = HashMap::new();
+    let mut came_from: HashMap<Point2<u32>, Point2<u32>> = HashMap::new();
+    let mut to_search = vec![start];
+    costs.insert(start, 0.0);
+
+    while let Some(tile) = to_search.pop() {
+        if must_stop(tile, start, step_costs) {
             continue;
         }
-
-        // tile looks good
-        if !targets.contains(&next) {
-            targets.push(next);
+        for next in path_step_tiles(tile, map.size(), step_costs) {
+            let cost = costs[&tile] + step_cost(tile, next, step_costs);
+            if cost > rules.player_move_distance as f32
+                || occupied.contains(&next)
+                || !map.in_field(next)
+            {
+                continue;
+            }
+            if costs.get(&next).map_or(true, |&best| cost < best) {
+                costs.insert(next, cost);
+                came_from.insert(next, tile);
+                to_search.push(next);
+            }
         }
+    }
+
+    if !came_from.contains_key(&target) {
+        return None;
+    }
+    let mut path = vec![target];
+    let mut step = came_from[&target];
+    while step != start {
+        path.push(step);
+        step = came_from[&step];
+    }
+    path.reverse();
+    Some(path)
+}
+
+/// Whether `team` has anyone left on the bench, given the team of everyone
+/// who is, and substitutions left to bring them on with.
+fn can_substitute(
+    team: PlayerTeam,
+    bench: &[PlayerTeam],
+    subs: &Substitutions,
+    rules: &Rules,
+) -> bool {
+    bench.contains(&team) && subs.made(team) < rules.max_substitutions
+}
+
+/// Whether a player takes part in play. A seriously injured player waiting
+/// to be replaced doesn't, and is left out of the players on the pitch.
+fn can_play(player_id: Entity, injuries: &ReadStorage<Injury>) -> bool {
+    injuries
+        .get(player_id)
+        .map_or(true, |injury| !injury.serious)
+}
+
+/// Sorts players' tiles, entities and teams row by row across the pitch,
+/// which unlike entity order is the same for both players of a match.
+fn sorted_player_tiles(
+    mut player_tiles: Vec<(Point2<u32>, Entity, PlayerTeam)>,
+) -> Vec<(Point2<u32>, Entity, PlayerTeam)> {
+    player_tiles.sort_by_key(|&(pos, _, _)| (pos.y, pos.x));
+    player_tiles
+}
+
+/// The runs made onto a through-ball to `target`, if the receiver can get
+/// there.
+fn plan_through_ball_runs(
+    receiver_id: Entity,
+    target: Point2<u32>,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> Option<Vec<(Entity, Vec<Point2<u32>>)>> {
+    let occupied: Vec<Point2<u32>> = player_tiles.iter().map(|&(pos, _, _)| pos).collect();
+    let &(receiver_pos, _, receiver_team) = player_tiles
+        .iter()
+        .find(|&&(_, player_id, _)| player_id == receiver_id)?;
+    let costs = run_step_costs(receiver_team, map, rules, player_tiles);
+    let receiver_path = find_run_path(receiver_pos, target, map, rules, &costs, &occupied)?;
+    Some(race_to_ball(
+        receiver_id,
+        receiver_path,
+        map,
+        rules,
+        rng,
+        player_tiles,
+    ))
+}
+
+/// The runs made onto a ball heading for the end of `receiver_path`: the
+/// receiver's, and the quickest opponent's if they get there first, in which
+/// case the receiver pulls up a tile short. Ties are settled at random.
+/// Nobody runs if the path is empty or the receiver isn't on the pitch.
+fn race_to_ball(
+    receiver_id: Entity,
+    mut receiver_path: Vec<Point2<u32>>,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> Vec<(Entity, Vec<Point2<u32>>)> {
+    let occupied: Vec<Point2<u32>> = player_tiles.iter().map(|&(pos, _, _)| pos).collect();
+    let receiver = player_tiles
+        .iter()
+        .find(|&&(_, player_id, _)| player_id == receiver_id);
+    let (receiver_pos, receiver_team, target) = match (receiver, receiver_path.last()) {
+        (Some(&(pos, _, team)), Some(&target)) => (pos, team, target),
+        _ => return Vec::new(),
+    };
+    let receiver_costs = run_step_costs(receiver_team, map, rules, player_tiles);
+    let receiver_cost = path_cost(receiver_pos, &receiver_path, &receiver_costs);
 
-        // queue adjacent tiles to be searched
-        for tile in get_adjacent_tiles(next, Vector2::new(map.map.width, map.map.height)) {
-            let should_search = !searched.contains_key(&tile)
-                || (searched.contains_key(&tile) && new_distance < searched[&tile]);
-            if should_search {
-                searched.insert(tile, new_distance);
-                to_search.push(tile);
+    let mut interceptor: Option<(Entity, Vec<Point2<u32>>, f32)> = None;
+    let mut interceptor_costs: Option<StepCosts> = None;
+    for &(pos, player_id, team) in player_tiles {
+        if team == receiver_team {
+            continue;
+        }
+        let costs =
+            interceptor_costs.get_or_insert_with(|| run_step_costs(team, map, rules, player_tiles));
+        if let Some(path) = find_run_path(pos, target, map, rules, costs, &occupied) {
+            let cost = path_cost(pos, &path, costs);
+            let quicker = interceptor
+                .as_ref()
+                .map_or(true, |&(_, _, best)| cost < best);
+            if quicker {
+                interceptor = Some((player_id, path, cost));
             }
         }
     }
 
-    targets
+    let intercepted = match interceptor {
+        Some((_, _, cost)) => cost < receiver_cost || (cost == receiver_cost && rng.chance(0.5)),
+        None => false,
+    };
+
+    let mut runs = Vec::new();
+    if intercepted {
+        receiver_path.pop();
+        runs.push((receiver_id, receiver_path));
+        runs.extend(interceptor.map(|(player_id, path, _)| (player_id, path)));
+    } else {
+        runs.push((receiver_id, receiver_path));
+    }
+    runs
+}
+
+/// Sets the carrier sprinting along `path`, and anyone racing them to the
+/// end of it, and returns the state of the ball knocked on ahead of them.
+fn knock_on<'a>(
+    player_id: Entity,
+    path: Vec<Point2<u32>>,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+    players: &mut WriteStorage<'a, Player>,
+) -> BallState {
+    let runs = race_to_ball(player_id, path.clone(), map, rules, rng, player_tiles);
+    start_runs(runs, players);
+    BallState::Moving {
+        player_id,
+        path,
+        // Only just hard enough to get there
+        power: 0.0,
+        shot: false,
+    }
+}
+
+fn start_runs<'a>(runs: Vec<(Entity, Vec<Point2<u32>>)>, players: &mut WriteStorage<'a, Player>) {
+    for (player_id, path) in runs {
+        if !path.is_empty() {
+            players.get_mut(player_id).unwrap().state = PlayerState::Running { path };
+        }
+    }
 }
 
 fn calculate_pass_targets(
     start_pos: Point2<u32>,
     map: &Map,
     max_distance: u32,
+    conditions: &Conditions,
 ) -> Vec<Point2<u32>> {
     let mut targets: Vec<Point2<u32>> = Vec::new();
+    let reaches = |i, x, y| i < conditions.pass_distance(max_distance, Vector2::new(x, y));
 
-    for i in 1..max_distance {
-        if start_pos.x >= i {
+    for i in 1..conditions.longest_pass(max_distance) {
+        if start_pos.x >= i && reaches(i, -1, 0) {
             targets.push(Point2::new(start_pos.x - i, start_pos.y));
         }
-        if start_pos.x + i < map.map.width {
+        if start_pos.x + i < map.map.width && reaches(i, 1, 0) {
             targets.push(Point2::new(start_pos.x + i, start_pos.y));
         }
-        if start_pos.y >= i {
+        if start_pos.y >= i && reaches(i, 0, -1) {
             targets.push(Point2::new(start_pos.x, start_pos.y - i));
         }
-        if start_pos.y + i < map.map.height {
+        if start_pos.y + i < map.map.height && reaches(i, 0, 1) {
             targets.push(Point2::new(start_pos.x, start_pos.y + i));
         }
     }
@@ -300,140 +879,1020 @@ fn calculate_pass_targets(
     targets
 }
 
-pub struct ActionMenuSystem;
+fn tile_distance(from: Point2<u32>, to: Point2<u32>) -> f32 {
+    let dx = to.x as f32 - from.x as f32;
+    let dy = to.y as f32 - from.y as f32;
+    (dx * dx + dy * dy).sqrt()
+}
 
-impl<'a> System<'a> for ActionMenuSystem {
-    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
-    type SystemData = (
-        Entities<'a>,
-        FetchMut<'a, Turn>,
-        Fetch<'a, Input>,
-        Fetch<'a, Map>,
-        ReadStorage<'a, Player>,
-        ReadStorage<'a, Ball>,
-        ReadStorage<'a, TilePosition>,
-        WriteStorage<'a, CanMove>,
-    );
+/// Whether two tiles touch, including diagonally.
+fn is_adjacent(a: Point2<u32>, b: Point2<u32>) -> bool {
+    a != b && (a.x as i32 - b.x as i32).abs() <= 1 && (a.y as i32 - b.y as i32).abs() <= 1
+}
 
-    fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut turn, input, map, players, balls, tile_positions, mut can_moves) = data;
+fn calculate_lofted_pass_targets(
+    start_pos: Point2<u32>,
+    map: &Map,
+    max_distance: u32,
+    conditions: &Conditions,
+) -> Vec<Point2<u32>> {
+    let mut targets: Vec<Point2<u32>> = Vec::new();
 
-        if let TurnState::ActionMenu { player_id } = turn.state {
-            if input.select {
-                // FIXME: run button clicked
-                let player_pos = tile_positions.get(player_id).unwrap().pos;
-                let dests = calculate_run_targets(
-                    player_pos,
-                    &map,
-                    PLAYER_MOVE_DISTANCE,
-                    &players,
-                    &tile_positions,
-                );
-                let can_move = CanMove {
-                    start: player_pos,
-                    distance: PLAYER_MOVE_DISTANCE,
-                    dests,
-                    path: Vec::new(),
-                };
-                can_moves.insert(player_id, can_move);
-                turn.state = TurnState::SelectRun { player_id };
+    for x in 0..map.map.width {
+        for y in 0..map.map.height {
+            let target = Point2::new(x, y);
+            let direction = Vector2::new(
+                target.x as i32 - start_pos.x as i32,
+                target.y as i32 - start_pos.y as i32,
+            );
+            let distance = conditions.pass_distance(max_distance, direction);
+            if target != start_pos && tile_distance(start_pos, target) <= distance as f32 {
+                targets.push(target);
             }
+        }
+    }
 
-            for (ball_id, ball) in (&*entities, &balls).join() {
-                // Why can't I do this? - if ball.state == BallState::Possessed { player_id }
-                if let BallState::Possessed {
-                    player_id: possessed_by,
-                } = ball.state
-                {
-                    if player_id == possessed_by {
-                        if false {
-                            // FIXME: pass button clicked
-                            let ball_pos = tile_positions.get(ball_id).unwrap().pos;
-                            let dests = calculate_pass_targets(ball_pos, &map, BALL_PASS_DISTANCE);
-                            let can_move = CanMove {
-                                start: ball_pos,
-                                distance: BALL_PASS_DISTANCE,
-                                dests,
-                                path: Vec::new(),
-                            };
-                            can_moves.insert(ball_id, can_move);
-                            turn.state = TurnState::SelectPass { player_id, ball_id };
-                        }
+    targets
+}
 
-                        // Can't pass more than one ball
-                        break;
-                    }
-                }
-            }
+/// Where a lofted pass aimed at `target` lands. It strays further the longer
+/// the pass and the less skilled the passer.
+fn lofted_pass_landing(
+    from: Point2<u32>,
+    target: Point2<u32>,
+    passing: u32,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+) -> Point2<u32> {
+    let inaccuracy = MAX_ATTRIBUTE.saturating_sub(passing) as f32 / MAX_ATTRIBUTE as f32;
+    let scatter =
+        (tile_distance(from, target) * rules.lofted_pass_scatter * inaccuracy).round() as i32;
 
-            if input.cancel {
-                // FIXME: cancel button clicked
-                turn.state = TurnState::SelectPlayer;
-            }
-        }
+    let x = target.x as i32 + rng.range(-scatter, scatter);
+    let y = target.y as i32 + rng.range(-scatter, scatter);
+    let landing = Point2::new(
+        x.max(0).min(map.map.width as i32 - 1) as u32,
+        y.max(0).min(map.map.height as i32 - 1) as u32,
+    );
+    // However wild the pass, it doesn't drop back at the passer's feet
+    if landing == from {
+        target
+    } else {
+        landing
     }
 }
 
-pub struct RunSelectSystem;
+/// How hard a pass over `distance` tiles is struck, from 0 up to 1 for the
+/// longest pass of its kind.
+fn pass_power(distance: f32, max_distance: u32) -> f32 {
+    // Passes the wind carries further aren't struck any harder
+    (distance / max_distance as f32).min(1.0)
+}
 
-impl<'a> System<'a> for RunSelectSystem {
-    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
-    type SystemData = (
-        Fetch<'a, Input>,
-        FetchMut<'a, Turn>,
-        WriteStorage<'a, CanMove>,
-        ReadStorage<'a, Cursor>,
-        ReadStorage<'a, TilePosition>,
-        WriteStorage<'a, Player>,
-    );
+/// The chance of a receiver bringing a pass under control. Harder passes
+/// are harder to control.
+fn first_touch_chance(control: u32, power: f32, rules: &Rules) -> f32 {
+    let skill = (control as f32 - MAX_ATTRIBUTE as f32 / 2.0) / MAX_ATTRIBUTE as f32;
+    (rules.first_touch_chance + skill - power * rules.first_touch_power_penalty)
+        .max(0.0)
+        .min(1.0)
+}
 
-    fn run(&mut self, data: Self::SystemData) {
-        let (input, mut turn, mut can_moves, cursors, tile_positions, mut players) = data;
+/// The chance of each opponent a dribbler runs next to winning the ball.
+fn tackle_chance(control: u32, rules: &Rules) -> f32 {
+    let skill = (control as f32 - MAX_ATTRIBUTE as f32 / 2.0) / MAX_ATTRIBUTE as f32;
+    (rules.dribble_tackle_chance - skill).max(0.0).min(1.0)
+}
 
-        if let TurnState::SelectRun { player_id } = turn.state {
-            // Find the player
-            let player = players.get_mut(player_id).unwrap();
+/// The chance of a challenge from `tackler` on a carrier at `carrier`
+/// heading in `direction` being a foul. Challenges from behind are the most
+/// dangerous.
+fn foul_chance(
+    carrier: Point2<u32>,
+    direction: Vector2<i32>,
+    tackler: Point2<u32>,
+    rules: &Rules,
+) -> f32 {
+    let offset = Vector2::new(
+        tackler.x as i32 - carrier.x as i32,
+        tackler.y as i32 - carrier.y as i32,
+    );
+    let facing = offset.x * direction.x + offset.y * direction.y;
+    if facing > 0 {
+        rules.foul_chance_front
+    } else if facing == 0 {
+        rules.foul_chance_side
+    } else {
+        rules.foul_chance_behind
+    }
+}
 
-            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
-                if input.select {
-                    if cursor.state == CursorState::Still {
-                        let at_end_of_path = {
-                            can_moves.get(player_id).unwrap().path.last() == Some(&cursor_pos.pos)
-                        };
-                        if at_end_of_path {
-                            turn.state = TurnState::Running { player_id };
-                            player.state = PlayerState::Running {
-                                path: can_moves.get(player_id).unwrap().path.clone(),
-                            };
-                            can_moves.remove(player_id).unwrap();
-                        }
-                    }
-                } else if input.cancel {
-                    can_moves.remove(player_id).unwrap();
-                    turn.state = TurnState::SelectPlayer;
-                }
-            }
+/// What comes of a foul by a player on `team` on a carrier at `tile`: any
+/// card they are shown, and the free kick or penalty given against them.
+fn foul<'a>(
+    fouler_id: Entity,
+    team: PlayerTeam,
+    tile: Point2<u32>,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    cards: &mut WriteStorage<'a, Cards>,
+) -> (MatchEvent, Restart) {
+    let roll = rng.next_f32();
+    let card = if roll < rules.red_card_chance {
+        Some(Card::Red)
+    } else if roll < rules.red_card_chance + rules.yellow_card_chance {
+        let booked = cards.get_mut(fouler_id).unwrap();
+        booked.yellow += 1;
+        if booked.yellow >= 2 {
+            Some(Card::SecondYellow)
+        } else {
+            Some(Card::Yellow)
         }
-    }
+    } else {
+        None
+    };
+
+    let penalty = map.in_penalty_area(team, tile);
+    let restart = Restart {
+        kind: if penalty {
+            RestartKind::Penalty
+        } else {
+            RestartKind::FreeKick
+        },
+        team: team.opponents(),
+        spot: if penalty {
+            map.penalty_spot(team)
+        } else {
+            tile
+        },
+    };
+    let event = MatchEvent::Foul {
+        team,
+        penalty,
+        card,
+    };
+    (event, restart)
 }
 
-pub struct PathSelectSystem;
+/// The offence and indirect free kick given if a pass along `path` by
+/// `player_id` is to a teammate in an offside position. Players can't be
+/// offside from throw-ins, goal kicks or corners.
+fn check_offside(
+    player_id: Entity,
+    path: &[Point2<u32>],
+    map: &Map,
+    rules: &Rules,
+    restart: &PendingRestart,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> Option<(MatchEvent, Restart)> {
+    match restart.taker {
+        Some((_, RestartKind::ThrowIn))
+        | Some((_, RestartKind::GoalKick))
+        | Some((_, RestartKind::Corner)) => return None,
+        _ => {}
+    }
+    if !rules.offside {
+        return None;
+    }
 
-impl<'a> System<'a> for PathSelectSystem {
-    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
-    type SystemData = (
-        Fetch<'a, Map>,
-        ReadStorage<'a, Cursor>,
-        ReadStorage<'a, TilePosition>,
-        WriteStorage<'a, CanMove>,
-    );
+    let &(from, _, team) = player_tiles
+        .iter()
+        .find(|&&(_, other_id, _)| other_id == player_id)
+        .unwrap();
+    let defenders: Vec<Point2<u32>> = player_tiles
+        .iter()
+        .filter(|&&(_, _, other_team)| other_team != team)
+        .map(|&(pos, _, _)| pos)
+        .collect();
+    let line = map.offside_line(team, from, &defenders);
 
-    fn run(&mut self, data: Self::SystemData) {
-        let (map, cursors, tile_positions, mut can_moves) = data;
+    let target = *path.last()?;
+    let receiver = player_tiles.iter().find(|&&(pos, other_id, other_team)| {
+        pos == target && other_id != player_id && other_team == team
+    })?;
+    if !map.is_offside(team, line, receiver.0) {
+        return None;
+    }
+    let restart = Restart {
+        kind: RestartKind::IndirectFreeKick,
+        team: team.opponents(),
+        spot: receiver.0,
+    };
+    Some((MatchEvent::Offside { team }, restart))
+}
 
-        for (_cursor, cursor_pos) in (&cursors, &tile_positions).join() {
-            for (can_move,) in (&mut can_moves,).join() {
-                if Some(&cursor_pos.pos) != can_move.path.last() {
+/// The tiles along a straight line from `from` to `to`, not including `from`.
+fn line_path(from: Point2<u32>, to: Point2<u32>) -> Vec<Point2<u32>> {
+    let dx = to.x as f32 - from.x as f32;
+    let dy = to.y as f32 - from.y as f32;
+    let steps = dx.abs().max(dy.abs()) as u32;
+    (1..steps + 1)
+        .map(|i| {
+            let t = i as f32 / steps as f32;
+            Point2::new(
+                (from.x as f32 + dx * t).round() as u32,
+                (from.y as f32 + dy * t).round() as u32,
+            )
+        })
+        .collect()
+}
+
+/// The middle of the goal that `team` shoots at.
+fn goal_target(team: PlayerTeam, map: &Map) -> Point2<u32> {
+    let mouth = map.goal_mouth(team.opponents());
+    mouth[mouth.len() / 2]
+}
+
+/// Where on the goal line a shot from `from` at `target` ends up, straying
+/// further the worse the shooter, the longer and wider the shot and the
+/// more opponents closing them down.
+fn aim(
+    from: Point2<u32>,
+    target: Point2<u32>,
+    shooting: u32,
+    pressure: usize,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+) -> Point2<u32> {
+    let distance = tile_distance(from, target);
+    // From 0 straight on up to 1 along the goal line
+    let angle = (target.y as f32 - from.y as f32).abs() / distance.max(1.0);
+    let inaccuracy = 0.5 + MAX_ATTRIBUTE.saturating_sub(shooting) as f32 / MAX_ATTRIBUTE as f32;
+    let scatter = distance * rules.shot_scatter * inaccuracy * (1.0 + angle)
+        + pressure as f32 * rules.shot_pressure_scatter;
+    let stray = ((rng.next_f32() * 2.0 - 1.0) * scatter).round() as i32;
+    let y = (target.y as i32 + stray)
+        .max(0)
+        .min(map.map.height as i32 - 1) as u32;
+    Point2::new(target.x, y)
+}
+
+/// A shot aimed at `target` on the goal line. It strays along the line
+/// further the longer the shot, the tighter the angle, the more opponents
+/// are closing the shooter down and the less skilled the shooter. Anyone in
+/// the way might then block it.
+fn shoot(
+    player_id: Entity,
+    target: Point2<u32>,
+    shooting: u32,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> BallState {
+    let &(from, _, team) = player_tiles
+        .iter()
+        .find(|&&(_, other_id, _)| other_id == player_id)
+        .unwrap();
+    let pressure = player_tiles
+        .iter()
+        .filter(|&&(pos, _, other_team)| other_team != team && is_adjacent(pos, from))
+        .count();
+    let landing = aim(from, target, shooting, pressure, map, rules, rng);
+
+    // Each player in the way has a go at blocking it in turn, drawing
+    // from the generator only for them
+    let mut path = line_path(from, landing);
+    let mut blocked_at = None;
+    for (i, step) in path.iter().enumerate() {
+        let in_the_way = player_tiles
+            .iter()
+            .any(|&(pos, other_id, _)| pos == *step && other_id != player_id);
+        if in_the_way && rng.chance(rules.shot_block_chance) {
+            blocked_at = Some(i);
+            break;
+        }
+    }
+    if let Some(i) = blocked_at {
+        path.truncate(i + 1);
+    }
+
+    BallState::Moving {
+        player_id,
+        path,
+        power: 1.0,
+        shot: true,
+    }
+}
+
+/// What happens when the ball goes out of play over `tile`, last touched by
+/// someone on `last_touch`, and how play restarts.
+fn out_of_play(tile: Point2<u32>, last_touch: PlayerTeam, map: &Map) -> (MatchEvent, Restart) {
+    for &defending in &[PlayerTeam::Red, PlayerTeam::Blue] {
+        if tile.x != map.goal_line(defending) {
+            continue;
+        }
+        let attacking = defending.opponents();
+        return if map.goal_mouth(defending).contains(&tile) {
+            let restart = Restart {
+                kind: RestartKind::KickOff,
+                team: defending,
+                spot: map.centre_spot(),
+            };
+            (MatchEvent::Goal { team: attacking }, restart)
+        } else if last_touch == defending {
+            let restart = Restart {
+                kind: RestartKind::Corner,
+                team: attacking,
+                spot: map.corner_spot(defending, tile),
+            };
+            (MatchEvent::Corner { team: attacking }, restart)
+        } else {
+            let restart = Restart {
+                kind: RestartKind::GoalKick,
+                team: defending,
+                spot: map.goal_kick_spot(defending),
+            };
+            (MatchEvent::Wide { team: attacking }, restart)
+        };
+    }
+
+    let team = last_touch.opponents();
+    let restart = Restart {
+        kind: RestartKind::ThrowIn,
+        team,
+        spot: map.throw_in_spot(tile),
+    };
+    (MatchEvent::ThrowIn { team }, restart)
+}
+
+/// The nearest tile to `pos` in the field which nobody is on and which is at
+/// least `distance` from `spot`, for moving players out of the way of a
+/// restart. Penalties also clear everyone out of the penalty area of
+/// `outside`.
+fn clear_tile(
+    pos: Point2<u32>,
+    spot: Point2<u32>,
+    distance: f32,
+    outside: Option<PlayerTeam>,
+    map: &Map,
+    occupied: &[Point2<u32>],
+) -> Point2<u32> {
+    let mut best = None;
+    for y in 0..map.map.height {
+        for x in 0..map.map.width {
+            let tile = Point2::new(x, y);
+            if !map.in_field(tile)
+                || occupied.contains(&tile)
+                || tile_distance(tile, spot) < distance
+                || outside.map_or(false, |team| map.in_penalty_area(team, tile))
+            {
+                continue;
+            }
+            let away = tile_distance(tile, pos);
+            if best.map_or(true, |(_, best_away)| away < best_away) {
+                best = Some((tile, away));
+            }
+        }
+    }
+    best.map_or(pos, |(tile, _)| tile)
+}
+
+fn report(event: MatchEvent, events: &mut MatchEvents, score: &mut Score) {
+    if let MatchEvent::Goal { team } = event {
+        score.add_goal(team);
+    }
+    events.events.push(event);
+}
+
+/// Whether a player is a goalkeeper inside their own penalty area, where
+/// they can use their hands.
+fn is_handling<'a>(
+    player_id: Entity,
+    pos: Point2<u32>,
+    team: PlayerTeam,
+    roles: &ReadStorage<'a, Role>,
+    map: &Map,
+) -> bool {
+    roles.get(player_id) == Some(&Role::Goalkeeper) && map.in_penalty_area(team, pos)
+}
+
+/// Every goalkeeper's tile, entity, team and handling, in the same order as
+/// `player_tiles`.
+fn goalkeepers<'a>(
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+    roles: &ReadStorage<'a, Role>,
+    attributes: &ReadStorage<'a, Attributes>,
+) -> Vec<(Point2<u32>, Entity, PlayerTeam, u32)> {
+    player_tiles
+        .iter()
+        .filter(|&&(_, player_id, _)| roles.get(player_id) == Some(&Role::Goalkeeper))
+        .map(|&(pos, player_id, team)| {
+            let handling = attributes.get(player_id).unwrap().handling;
+            (pos, player_id, team, handling)
+        })
+        .collect()
+}
+
+/// Whether a goalkeeper on `team` standing on `keeper_pos` can get their
+/// hands to a ball on `tile`.
+fn within_reach(
+    keeper_pos: Point2<u32>,
+    team: PlayerTeam,
+    tile: Point2<u32>,
+    map: &Map,
+    rules: &Rules,
+) -> bool {
+    map.in_penalty_area(team, tile) && tile_distance(keeper_pos, tile) <= rules.keeper_reach as f32
+}
+
+/// The chance of a goalkeeper stopping a ball `distance` tiles away. It
+/// falls off towards the edge of their reach and for harder strikes.
+fn save_chance(handling: u32, distance: f32, power: f32, rules: &Rules) -> f32 {
+    let skill = (handling as f32 - MAX_ATTRIBUTE as f32 / 2.0) / MAX_ATTRIBUTE as f32;
+    let stretch = distance / (rules.keeper_reach as f32 + 1.0);
+    ((rules.keeper_save_chance + skill - power * rules.keeper_power_penalty)
+        * (1.0 - stretch / 2.0))
+        .max(0.0)
+        .min(1.0)
+}
+
+/// The unit step in tile space, including diagonals, heading from `from`
+/// towards `to`.
+fn step_towards(from: Point2<u32>, to: Point2<u32>) -> Vector2<i32> {
+    Vector2::new(
+        (to.x as i32 - from.x as i32).signum(),
+        (to.y as i32 - from.y as i32).signum(),
+    )
+}
+
+/// A loose ball rolling away from `start` in `direction` at `speed` tiles
+/// per second. It slows to a stop over a number of whole tiles decided up
+/// front, and stops early on reaching a player or the edge of the pitch.
+fn roll(
+    player_id: Entity,
+    start: Point2<u32>,
+    direction: Vector2<i32>,
+    speed: f32,
+    map: &Map,
+    rules: &Rules,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> BallState {
+    let distance = (speed * speed / (2.0 * rules.roll_deceleration)).floor() as u32;
+    let size = map.size();
+
+    let mut path = Vec::new();
+    let mut pos = start;
+    for _ in 0..distance {
+        let x = pos.x as i32 + direction.x;
+        let y = pos.y as i32 + direction.y;
+        if x < 0 || y < 0 || x >= size.x as i32 || y >= size.y as i32 {
+            break;
+        }
+        pos = Point2::new(x as u32, y as u32);
+        path.push(pos);
+        if !map.in_field(pos) {
+            break;
+        }
+        if player_tiles
+            .iter()
+            .any(|&(player_pos, _, _)| player_pos == pos)
+        {
+            break;
+        }
+    }
+
+    if path.is_empty() || direction == Vector2::new(0, 0) {
+        BallState::Free
+    } else {
+        BallState::Rolling {
+            player_id,
+            path,
+            speed,
+        }
+    }
+}
+
+/// A ball coming to rest on `pos` is at the feet of whoever is there.
+fn settle(pos: Point2<u32>, player_tiles: &[(Point2<u32>, Entity, PlayerTeam)]) -> BallState {
+    match player_tiles
+        .iter()
+        .find(|&&(player_pos, _, _)| player_pos == pos)
+    {
+        Some(&(_, player_id, _)) => BallState::Possessed { player_id },
+        None => BallState::Free,
+    }
+}
+
+// The effect of each action, shared by the systems which choose it and the
+// replay of an opponent's turn so that both play it out the same way.
+
+/// Sets `player_id` running along `path`.
+fn apply_run<'a>(
+    player_id: Entity,
+    path: Vec<Point2<u32>>,
+    players: &mut WriteStorage<'a, Player>,
+) -> TurnState {
+    players.get_mut(player_id).unwrap().state = PlayerState::Running { path };
+    TurnState::Running { player_id }
+}
+
+/// Plays the ball along `path`, unless it is to a teammate in an offside
+/// position, in which case the other side get a free kick instead.
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+fn apply_pass(
+    player_id: Entity,
+    ball_id: Entity,
+    ball: &mut Ball,
+    path: Vec<Point2<u32>>,
+    map: &Map,
+    rules: &Rules,
+    restart: &PendingRestart,
+    events: &mut MatchEvents,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> TurnState {
+    let offside = check_offside(player_id, &path, map, rules, restart, player_tiles);
+    if let Some((event, awarded)) = offside {
+        events.events.push(event);
+        return TurnState::Restart { restart: awarded };
+    }
+    let power = pass_power(path.len() as f32, rules.ball_pass_distance);
+    ball.state = BallState::Moving {
+        player_id,
+        path,
+        power,
+        shot: false,
+    };
+    TurnState::Passing { player_id, ball_id }
+}
+
+/// Lofts the ball from `from` towards `target`, to land wherever the
+/// passer's skill takes it, unless it is meant for a teammate in an offside
+/// position.
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+fn apply_lofted_pass(
+    player_id: Entity,
+    ball_id: Entity,
+    ball: &mut Ball,
+    from: Point2<u32>,
+    target: Point2<u32>,
+    passing: u32,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    restart: &PendingRestart,
+    events: &mut MatchEvents,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> TurnState {
+    let offside = check_offside(player_id, &[target], map, rules, restart, player_tiles);
+    if let Some((event, awarded)) = offside {
+        events.events.push(event);
+        return TurnState::Restart { restart: awarded };
+    }
+    let to = lofted_pass_landing(from, target, passing, map, rules, rng);
+    ball.state = BallState::Lofted {
+        player_id,
+        from,
+        to,
+        elapsed: 0.0,
+    };
+    TurnState::Passing { player_id, ball_id }
+}
+
+/// Plays the ball along `path` for `receiver_id` to run onto, if they can
+/// get to the end of it.
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+fn apply_through_ball<'a>(
+    player_id: Entity,
+    ball_id: Entity,
+    ball: &mut Ball,
+    receiver_id: Entity,
+    path: Vec<Point2<u32>>,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+    players: &mut WriteStorage<'a, Player>,
+) -> Option<TurnState> {
+    let target = *path.last()?;
+    let runs = plan_through_ball_runs(receiver_id, target, map, rules, rng, player_tiles)?;
+    start_runs(runs, players);
+    let power = pass_power(path.len() as f32, rules.ball_pass_distance);
+    ball.state = BallState::Moving {
+        player_id,
+        path,
+        power,
+        shot: false,
+    };
+    Some(TurnState::ThroughBall { player_id, ball_id })
+}
+
+/// Knocks the ball on along `path` for the carrier to chase.
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+fn apply_knock_on<'a>(
+    player_id: Entity,
+    ball_id: Entity,
+    ball: &mut Ball,
+    path: Vec<Point2<u32>>,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+    players: &mut WriteStorage<'a, Player>,
+) -> TurnState {
+    ball.state = knock_on(player_id, path, map, rules, rng, player_tiles, players);
+    TurnState::KnockOn { player_id, ball_id }
+}
+
+/// Shoots at `target` on the goal line.
+#[cfg_attr(feature = "cargo-clippy", allow(clippy::too_many_arguments))]
+fn apply_shot(
+    player_id: Entity,
+    ball_id: Entity,
+    ball: &mut Ball,
+    target: Point2<u32>,
+    shooting: u32,
+    map: &Map,
+    rules: &Rules,
+    rng: &mut Rng,
+    player_tiles: &[(Point2<u32>, Entity, PlayerTeam)],
+) -> TurnState {
+    ball.state = shoot(player_id, target, shooting, map, rules, rng, player_tiles);
+    TurnState::Passing { player_id, ball_id }
+}
+
+/// Brings on the `reserve`th of `bench` in place of `player_id`, if there
+/// is one.
+fn apply_substitute(player_id: Entity, reserve: usize, bench: &[Entity]) -> Option<TurnState> {
+    bench
+        .get(reserve)
+        .map(|&reserve_id| TurnState::Substitution {
+            player_id,
+            reserve_id,
+        })
+}
+
+/// `team` commit to where their goalkeeper dives for the next kick of the
+/// shootout, which the other side then take.
+fn apply_shootout_dive(team: PlayerTeam, commitment: u64, shootout: &mut Shootout) -> TurnState {
+    shootout.commitment = Some(commitment);
+    TurnState::ShootoutTaker {
+        team: team.opponents(),
+    }
+}
+
+/// `taker_id` aims a kick in the shootout at `target`, to be taken once the
+/// goalkeeper's dive is revealed.
+fn apply_shootout_kick(taker_id: Entity, target: Point2<u32>) -> TurnState {
+    TurnState::ShootoutReveal { taker_id, target }
+}
+
+/// The goalkeeper's dive to `dive` is revealed and the kick taken, as long
+/// as it is the one committed to.
+fn apply_shootout_reveal(
+    taker_id: Entity,
+    target: Point2<u32>,
+    dive: Point2<u32>,
+    salt: u64,
+    shootout: &mut Shootout,
+) -> Option<TurnState> {
+    if shootout.commitment != Some(turn_file::dive_commitment(dive, salt)) {
+        return None;
+    }
+    shootout.commitment = None;
+    shootout.dive = Some(dive);
+    Some(TurnState::PenaltyKick { taker_id, target })
+}
+
+pub struct ActionMenuSystem;
+
+impl<'a> System<'a> for ActionMenuSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        FetchMut<'a, Turn>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, Conditions>,
+        Fetch<'a, PendingRestart>,
+        Fetch<'a, Substitutions>,
+        FetchMut<'a, ActionMenu>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Bench>,
+        ReadStorage<'a, Injury>,
+        ReadStorage<'a, Ball>,
+        ReadStorage<'a, TilePosition>,
+        WriteStorage<'a, CanMove>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            mut turn,
+            input,
+            map,
+            rules,
+            conditions,
+            restart,
+            subs,
+            mut menu,
+            players,
+            benched,
+            injuries,
+            balls,
+            tile_positions,
+            mut can_moves,
+        ) = data;
+
+        if let TurnState::ActionMenu { player_id } = turn.state {
+            // Can't pass more than one ball
+            let ball_id = (&*entities, &balls)
+                .join()
+                .find(|&(_, ball)| ball.state == BallState::Possessed { player_id })
+                .map(|(ball_id, _)| ball_id);
+
+            // Shots have to be from in range but not from the goal line
+            let in_range = ball_id.map_or(false, |ball_id| {
+                let ball_pos = tile_positions.get(ball_id).unwrap().pos;
+                let team = players.get(player_id).unwrap().team;
+                let target = goal_target(team, &map);
+                tile_distance(ball_pos, target) <= rules.shot_range as f32 && ball_pos.x != target.x
+            });
+
+            // Seriously injured players can only be substituted
+            let injured = injuries
+                .get(player_id)
+                .map_or(false, |injury| injury.serious);
+
+            menu.actions.clear();
+            match restart.taker {
+                _ if injured => {}
+                // Only the taker can play on from a restart
+                Some((taker_id, _)) if taker_id != player_id => {}
+                // Penalties have to be shot, and other restarts passed
+                // unless a free kick is close enough to shoot from
+                Some((_, RestartKind::Penalty)) => {
+                    menu.actions.push(MenuAction::Shoot);
+                }
+                Some((_, kind)) => {
+                    menu.actions.push(MenuAction::Pass);
+                    menu.actions.push(MenuAction::LoftedPass);
+                    menu.actions.push(MenuAction::ThroughBall);
+                    if kind == RestartKind::FreeKick && in_range {
+                        menu.actions.push(MenuAction::Shoot);
+                    }
+                }
+                None if ball_id.is_some() => {
+                    menu.actions.push(MenuAction::Run);
+                    menu.actions.push(MenuAction::Pass);
+                    menu.actions.push(MenuAction::LoftedPass);
+                    menu.actions.push(MenuAction::ThroughBall);
+                    menu.actions.push(MenuAction::KnockOn);
+                    if in_range {
+                        menu.actions.push(MenuAction::Shoot);
+                    }
+                }
+                None => {
+                    menu.actions.push(MenuAction::Run);
+                }
+            }
+            // Players can only be swapped while play is stopped, unless
+            // they are too badly hurt to carry on
+            let team = players.get(player_id).unwrap().team;
+            let bench: Vec<_> = (&players, &benched)
+                .join()
+                .map(|(reserve, _)| reserve.team)
+                .collect();
+            if (restart.taker.is_some() || injured) && can_substitute(team, &bench, &subs, &rules) {
+                menu.actions.push(MenuAction::Substitute);
+            }
+            if menu.actions.is_empty() {
+                turn.state = TurnState::SelectPlayer;
+                return;
+            }
+            if menu.selected >= menu.actions.len() {
+                menu.selected = 0;
+            }
+
+            if input.was_pressed(Action::CursorUp) && menu.selected > 0 {
+                menu.selected -= 1;
+            }
+            if input.was_pressed(Action::CursorDown) && menu.selected + 1 < menu.actions.len() {
+                menu.selected += 1;
+            }
+
+            if input.was_pressed(Action::Select) {
+                match menu.actions[menu.selected] {
+                    MenuAction::Run => {
+                        let player_pos = tile_positions.get(player_id).unwrap().pos;
+                        let player_tiles: Vec<_> = (&*entities, &players, &tile_positions)
+                            .join()
+                            .map(|(other_id, other, pos)| (pos.pos, other_id, other.team))
+                            .collect();
+                        let team = players.get(player_id).unwrap().team;
+                        let costs = run_step_costs(team, &map, &rules, &player_tiles);
+                        // The ball slows players down
+                        let distance = if ball_id.is_some() {
+                            rules.dribble_move_distance
+                        } else {
+                            rules.player_move_distance
+                        };
+                        let dests = calculate_run_targets(
+                            player_pos,
+                            &map,
+                            distance,
+                            &costs,
+                            &players,
+                            &tile_positions,
+                        );
+                        let can_move = CanMove {
+                            start: player_pos,
+                            distance,
+                            costs,
+                            dests,
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(player_id, can_move);
+                        turn.state = TurnState::SelectRun { player_id };
+                    }
+                    MenuAction::Pass => {
+                        let ball_id = ball_id.unwrap();
+                        let ball_pos = tile_positions.get(ball_id).unwrap().pos;
+                        let dests = calculate_pass_targets(
+                            ball_pos,
+                            &map,
+                            rules.ball_pass_distance,
+                            &conditions,
+                        );
+                        let can_move = CanMove {
+                            start: ball_pos,
+                            distance: conditions.longest_pass(rules.ball_pass_distance),
+                            costs: StepCosts::default(),
+                            dests,
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(ball_id, can_move);
+                        turn.state = TurnState::SelectPass { player_id, ball_id };
+                    }
+                    MenuAction::LoftedPass => {
+                        let ball_id = ball_id.unwrap();
+                        let ball_pos = tile_positions.get(ball_id).unwrap().pos;
+                        let dests = calculate_lofted_pass_targets(
+                            ball_pos,
+                            &map,
+                            rules.lofted_pass_distance,
+                            &conditions,
+                        );
+                        let can_move = CanMove {
+                            start: ball_pos,
+                            distance: conditions.longest_pass(rules.lofted_pass_distance),
+                            costs: StepCosts::default(),
+                            dests,
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(ball_id, can_move);
+                        turn.state = TurnState::SelectLoftedPass { player_id, ball_id };
+                    }
+                    MenuAction::ThroughBall => {
+                        // Through-balls are played into space
+                        let ball_id = ball_id.unwrap();
+                        let ball_pos = tile_positions.get(ball_id).unwrap().pos;
+                        let dests = calculate_pass_targets(
+                            ball_pos,
+                            &map,
+                            rules.ball_pass_distance,
+                            &conditions,
+                        )
+                        .into_iter()
+                        .filter(|dest| {
+                            !(&players, &tile_positions)
+                                .join()
+                                .any(|(_, pos)| pos.pos == *dest)
+                        })
+                        .collect();
+                        let can_move = CanMove {
+                            start: ball_pos,
+                            distance: conditions.longest_pass(rules.ball_pass_distance),
+                            costs: StepCosts::default(),
+                            dests,
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(ball_id, can_move);
+                        turn.state = TurnState::SelectThroughBall { player_id, ball_id };
+                    }
+                    MenuAction::KnockOn => {
+                        // The player runs further without the ball at their
+                        // feet, and the path is theirs rather than the ball's
+                        let ball_id = ball_id.unwrap();
+                        let player_pos = tile_positions.get(player_id).unwrap().pos;
+                        let player_tiles: Vec<_> = (&*entities, &players, &tile_positions)
+                            .join()
+                            .map(|(other_id, other, pos)| (pos.pos, other_id, other.team))
+                            .collect();
+                        let team = players.get(player_id).unwrap().team;
+                        let costs = run_step_costs(team, &map, &rules, &player_tiles);
+                        let dests = calculate_run_targets(
+                            player_pos,
+                            &map,
+                            rules.knock_on_distance,
+                            &costs,
+                            &players,
+                            &tile_positions,
+                        );
+                        let can_move = CanMove {
+                            start: player_pos,
+                            distance: rules.knock_on_distance,
+                            costs,
+                            dests,
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(player_id, can_move);
+                        turn.state = TurnState::SelectKnockOn { player_id, ball_id };
+                    }
+                    MenuAction::Shoot => {
+                        let ball_id = ball_id.unwrap();
+                        let ball_pos = tile_positions.get(ball_id).unwrap().pos;
+                        let team = players.get(player_id).unwrap().team;
+                        let can_move = CanMove {
+                            start: ball_pos,
+                            distance: rules.shot_range,
+                            costs: StepCosts::default(),
+                            dests: map.goal_mouth(team.opponents()),
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(ball_id, can_move);
+                        turn.state = TurnState::SelectShot { player_id, ball_id };
+                    }
+                    MenuAction::Substitute => {
+                        turn.state = TurnState::SelectSubstitute {
+                            player_id,
+                            selected: 0,
+                        };
+                    }
+                }
+                menu.selected = 0;
+            } else if input.was_pressed(Action::Cancel) {
+                menu.selected = 0;
+                turn.state = TurnState::SelectPlayer;
+            }
+        }
+    }
+}
+
+pub struct RunSelectSystem;
+
+impl<'a> System<'a> for RunSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Fetch<'a, Input>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        WriteStorage<'a, CanMove>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        WriteStorage<'a, Player>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (input, mut turn, mut turn_log, mut can_moves, cursors, tile_positions, mut players) =
+            data;
+
+        if let TurnState::SelectRun { player_id } = turn.state {
+            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+                if input.was_pressed(Action::Select) {
+                    if cursor.state == CursorState::Still {
+                        let at_end_of_path = {
+                            can_moves.get(player_id).unwrap().path.last() == Some(&cursor_pos.pos)
+                        };
+                        if at_end_of_path {
+                            let can_move = can_moves.remove(player_id).unwrap();
+                            turn_log.actions.push(TurnAction::Run {
+                                from: can_move.start,
+                                path: can_move.path.clone(),
+                            });
+                            turn.state = apply_run(player_id, can_move.path, &mut players);
+                        }
+                    }
+                } else if input.was_pressed(Action::Cancel) {
+                    can_moves.remove(player_id).unwrap();
+                    turn.state = TurnState::SelectPlayer;
+                }
+            }
+        }
+    }
+}
+
+pub struct PathSelectSystem;
+
+impl<'a> System<'a> for PathSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Fetch<'a, Map>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        WriteStorage<'a, CanMove>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, cursors, tile_positions, mut can_moves) = data;
+
+        for (_cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+            for (can_move,) in (&mut can_moves,).join() {
+                if Some(&cursor_pos.pos) != can_move.path.last() {
                     // If we're back on the start position clear the path
                     if cursor_pos.pos == can_move.start {
                         can_move.path.clear();
@@ -447,15 +1906,18 @@ impl<'a> System<'a> for PathSelectSystem {
                     {
                         // If we cross our existing path then shrink back
                         can_move.path.truncate(i + 1);
-                    } else if get_adjacent_tiles(
-                        *can_move.path.last().unwrap_or(&can_move.start),
-                        Vector2::new(map.map.width, map.map.height),
-                    )
-                    .contains(&cursor_pos.pos)
-                        && can_move.path.len() < can_move.distance as usize
-                    {
-                        // We've only moved by 1 tile and the path isn't too long
-                        can_move.path.push(cursor_pos.pos);
+                    } else {
+                        let last = *can_move.path.last().unwrap_or(&can_move.start);
+                        let cost = path_cost(can_move.start, &can_move.path, &can_move.costs)
+                            + step_cost(last, cursor_pos.pos, &can_move.costs);
+                        if path_step_tiles(last, map.size(), &can_move.costs)
+                            .contains(&cursor_pos.pos)
+                            && cost <= can_move.distance as f32
+                            && !must_stop(last, can_move.start, &can_move.costs)
+                        {
+                            // We've only moved by 1 tile and the path isn't too long
+                            can_move.path.push(cursor_pos.pos);
+                        }
                     }
                 }
             }
@@ -468,21 +1930,47 @@ pub struct PlayerMovementSystem;
 impl<'a> System<'a> for PlayerMovementSystem {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
     type SystemData = (
+        Entities<'a>,
         Fetch<'a, DeltaTime>,
-        FetchMut<'a, Turn>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Steps>,
         WriteStorage<'a, Player>,
         WriteStorage<'a, TilePosition>,
         WriteStorage<'a, SubTilePosition>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (dt, mut turn, mut players, mut tile_positions, mut sub_tile_positions) = data;
+        let (
+            entities,
+            dt,
+            map,
+            rules,
+            mut steps,
+            mut players,
+            mut tile_positions,
+            mut sub_tile_positions,
+        ) = data;
+
+        steps.taken.clear();
+        let mut set_off = false;
 
-        for (player, tile_position, sub_tile_position) in
-            (&mut players, &mut tile_positions, &mut sub_tile_positions).join()
+        for (player_id, player, tile_position, sub_tile_position) in (
+            &*entities,
+            &mut players,
+            &mut tile_positions,
+            &mut sub_tile_positions,
+        )
+            .join()
         {
             let mut finished_run = false;
             if let PlayerState::Running { ref mut path } = player.state {
+                if !steps.trails.iter().any(|trail| trail.0 == player_id) {
+                    steps
+                        .trails
+                        .push((player_id, player.team, vec![tile_position.pos]));
+                    set_off = true;
+                }
                 let mut remaining_dt = dt.dt;
 
                 while !finished_run && remaining_dt > 0.0 {
@@ -490,10 +1978,10 @@ impl<'a> System<'a> for PlayerMovementSystem {
                         None => break,
                         Some(target) => *target,
                     };
-                    let disp = tile_to_subtile(target) - sub_tile_position.pos;
+                    let disp = map.tile_to_world(target) - sub_tile_position.pos;
 
                     if disp != Vector2::new(0.0, 0.0) {
-                        let velocity = disp.normalize() * PLAYER_SPEED;
+                        let velocity = velocity_towards(disp, map.tile_size(), rules.player_speed);
 
                         let required_dt_x = required_time(disp.x, velocity.x);
                         let required_dt_y = required_time(disp.y, velocity.y);
@@ -506,9 +1994,15 @@ impl<'a> System<'a> for PlayerMovementSystem {
                         remaining_dt -= remaining_dt_x.max(remaining_dt_y);
                     }
 
-                    if sub_tile_position.pos == tile_to_subtile(target) {
+                    if sub_tile_position.pos == map.tile_to_world(target) {
                         tile_position.pos = target;
                         path.remove(0);
+                        steps.taken.push((player_id, target));
+                        if let Some(trail) =
+                            steps.trails.iter_mut().find(|trail| trail.0 == player_id)
+                        {
+                            trail.2.push(target);
+                        }
                     }
 
                     if path.is_empty() {
@@ -519,9 +2013,114 @@ impl<'a> System<'a> for PlayerMovementSystem {
 
             if finished_run {
                 player.state = PlayerState::Still;
-                turn.state = TurnState::SelectPlayer;
             }
         }
+
+        // Both players of a match go through the runners in the same order
+        if set_off {
+            steps
+                .trails
+                .sort_by_key(|&(_, _, ref trail)| (trail[0].y, trail[0].x));
+        }
+    }
+}
+
+/// Works through the `trails` of opposing runners from step `checked` on, as
+/// far as all of those still `going` have got, for the pairs whose paths
+/// meet on the way: on the same tile after as many steps, or swapping tiles
+/// in the same step. Runners who have stopped stay on their last tile. Each
+/// pair only meets once, and are added to `met` when they do. Returns how
+/// many steps have now been checked.
+fn meet_along_trails(
+    trails: &[(Entity, PlayerTeam, Vec<Point2<u32>>)],
+    going: &[bool],
+    checked: usize,
+    met: &mut Vec<(Entity, Entity)>,
+) -> usize {
+    // Every step up to the one the slowest runner still going has reached,
+    // or to the end once they have all stopped
+    let last_step = |trail: &[Point2<u32>]| trail.len() - 1;
+    let still_going = trails
+        .iter()
+        .zip(going)
+        .filter(|&(_, &is_going)| is_going)
+        .map(|(&(_, _, ref trail), _)| last_step(trail))
+        .min();
+    let reached = match still_going {
+        Some(step) => step,
+        None => trails
+            .iter()
+            .map(|&(_, _, ref trail)| last_step(trail))
+            .max()
+            .unwrap_or(0),
+    };
+    let at = |trail: &[Point2<u32>], step: usize| trail[step.min(last_step(trail))];
+
+    for step in checked + 1..reached + 1 {
+        for (i, &(player_id, team, ref trail)) in trails.iter().enumerate() {
+            for &(other_id, other_team, ref other_trail) in &trails[i + 1..] {
+                if other_team == team || met.contains(&(player_id, other_id)) {
+                    continue;
+                }
+                let (from, to) = (at(trail, step - 1), at(trail, step));
+                let (other_from, other_to) = (at(other_trail, step - 1), at(other_trail, step));
+                if to == other_to || (to == other_from && other_to == from) {
+                    met.push((player_id, other_id));
+                }
+            }
+        }
+    }
+    reached.max(checked)
+}
+
+/// Opposing runners whose paths meet run into each other. This is worked
+/// out a step at a time along their paths, however many steps they take in
+/// a frame, and only once any tackles on the way have been played out.
+pub struct CollisionSystem;
+
+impl<'a> System<'a> for CollisionSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        FetchMut<'a, Steps>,
+        FetchMut<'a, Challenges>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, mut steps, mut challenges, players, tile_positions, injuries) = data;
+
+        // Anyone sent off or hurt since has dropped out of play
+        steps.trails.retain(|&(player_id, _, _)| {
+            entities.is_alive(player_id)
+                && tile_positions.get(player_id).is_some()
+                && can_play(player_id, &injuries)
+        });
+        let going: Vec<bool> = steps
+            .trails
+            .iter()
+            .map(
+                |&(player_id, _, _)| match players.get(player_id).unwrap().state {
+                    PlayerState::Running { .. } => true,
+                    PlayerState::Still => false,
+                },
+            )
+            .collect();
+
+        let Steps {
+            ref trails,
+            ref mut checked,
+            ref mut met,
+            ..
+        } = *steps;
+        let earlier = met.len();
+        *checked = meet_along_trails(trails, &going, *checked, met);
+        for &(player_id, other_id) in &met[earlier..] {
+            challenges.players.push((player_id, false));
+            challenges.players.push((other_id, false));
+        }
     }
 }
 
@@ -531,76 +2130,456 @@ impl<'a> System<'a> for BallDribbleSystem {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
     type SystemData = (
         Entities<'a>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, PendingRestart>,
+        FetchMut<'a, MatchEvents>,
+        FetchMut<'a, Challenges>,
+        FetchMut<'a, Steps>,
         WriteStorage<'a, Ball>,
-        ReadStorage<'a, Player>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Role>,
+        ReadStorage<'a, Attributes>,
+        WriteStorage<'a, Cards>,
         WriteStorage<'a, TilePosition>,
         WriteStorage<'a, SubTilePosition>,
+        ReadStorage<'a, Injury>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (entities, mut balls, players, mut tile_positions, mut sub_tile_positions) = data;
+        let (
+            entities,
+            map,
+            rules,
+            mut rng,
+            mut restart,
+            mut events,
+            mut challenges,
+            mut steps,
+            mut balls,
+            mut players,
+            roles,
+            attributes,
+            mut cards,
+            mut tile_positions,
+            mut sub_tile_positions,
+            injuries,
+        ) = data;
+
+        let player_tiles = sorted_player_tiles(
+            (&*entities, &players, &tile_positions)
+                .join()
+                .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                .collect(),
+        );
 
         for (ball_id, ball) in (&*entities, &mut balls).join() {
             match ball.state {
                 BallState::Free => {
                     let ball_pos = tile_positions.get(ball_id).unwrap();
 
-                    for (player_id, _) in (&*entities, &players).join() {
-                        let player_pos = tile_positions.get(player_id).unwrap();
+                    for (player_id, _, player_pos) in (&*entities, &players, &tile_positions).join()
+                    {
                         if ball_pos.pos == player_pos.pos {
                             ball.state = BallState::Possessed { player_id };
                         }
                     }
                 }
                 BallState::Possessed { player_id } => {
-                    let player_tile_pos = tile_positions.get(player_id).unwrap().pos;
-                    let player_subtile_pos = sub_tile_positions.get(player_id).unwrap().pos;
+                    let mut player_id = player_id;
 
-                    tile_positions.get_mut(ball_id).unwrap().pos = player_tile_pos;
-                    sub_tile_positions.get_mut(ball_id).unwrap().pos = player_subtile_pos;
-                }
-                _ => {}
-            }
-        }
-    }
-}
+                    // The ball is still where the carrier was until it
+                    // catches up with them, and at each tile they have
+                    // stepped onto since then every opponent next to them
+                    // has a go at winning the ball, and might foul them
+                    // doing so. Goalkeepers carrying it in their hands can't
+                    // be tackled.
+                    let team = players.get(player_id).unwrap().team;
+                    let ball_tile_pos = tile_positions.get(ball_id).unwrap().pos;
+                    let carried: Vec<Point2<u32>> = steps
+                        .taken
+                        .iter()
+                        .filter(|&&(runner_id, _)| runner_id == player_id)
+                        .map(|&(_, pos)| pos)
+                        .collect();
+                    // Only the steps taken since they picked the ball up
+                    let first = carried
+                        .iter()
+                        .rposition(|&pos| pos == ball_tile_pos)
+                        .map_or(0, |i| i + 1);
+                    let control = attributes.get(player_id).unwrap().control;
+                    let mut from = ball_tile_pos;
+                    let mut fouled_by = None;
+                    let mut won_by = None;
+                    let mut challenged_at = from;
+                    let mut unplayed = 0;
+                    for (step, &pos) in carried.iter().enumerate().skip(first) {
+                        if is_handling(player_id, pos, team, &roles, &map) {
+                            from = pos;
+                            continue;
+                        }
+                        let direction = step_towards(from, pos);
+                        let mut tacklers = Vec::new();
+                        for &(tackler_pos, tackler_id, other_team) in &player_tiles {
+                            if other_team == team || !is_adjacent(tackler_pos, pos) {
+                                continue;
+                            }
+                            let chance = foul_chance(pos, direction, tackler_pos, &rules);
+                            if rng.chance(chance) {
+                                fouled_by = Some((tackler_id, other_team));
+                                break;
+                            }
+                            tacklers.push(tackler_id);
+                            if rng.chance(tackle_chance(control, &rules)) {
+                                won_by = Some((tackler_id, tackler_pos));
+                                break;
+                            }
+                        }
 
-pub struct PassSelectSystem;
+                        // Anyone in the challenge might get hurt, the
+                        // carrier more likely so if they are fouled
+                        if fouled_by.is_some() || !tacklers.is_empty() {
+                            challenges.players.push((player_id, fouled_by.is_some()));
+                        }
+                        for tackler_id in tacklers {
+                            challenges.players.push((tackler_id, false));
+                        }
+                        challenged_at = pos;
+                        if fouled_by.is_some() || won_by.is_some() {
+                            unplayed = carried.len() - step - 1;
+                            break;
+                        }
+                        from = pos;
+                    }
+
+                    // A won tackle knocks the ball on away from the tackler,
+                    // who keeps it if it has nowhere to go
+                    if let Some((tackler_id, tackler_pos)) = won_by {
+                        ball.state = roll(
+                            tackler_id,
+                            challenged_at,
+                            step_towards(tackler_pos, challenged_at),
+                            rules.roll_speed * TACKLE_POWER,
+                            &map,
+                            &rules,
+                            &player_tiles,
+                        );
+                        if ball.state == BallState::Free {
+                            player_id = tackler_id;
+                            ball.state = BallState::Possessed { player_id };
+                        } else {
+                            tile_positions.get_mut(ball_id).unwrap().pos = challenged_at;
+                            sub_tile_positions.get_mut(ball_id).unwrap().pos =
+                                map.tile_to_world(challenged_at);
+                            continue;
+                        }
+                    }
+                    if let Some((fouler_id, fouler_team)) = fouled_by {
+                        // The carrier is brought down where they were
+                        // fouled, however far they have got since, and can't
+                        // run into anyone further on
+                        players.get_mut(player_id).unwrap().state = PlayerState::Still;
+                        tile_positions.get_mut(player_id).unwrap().pos = challenged_at;
+                        sub_tile_positions.get_mut(player_id).unwrap().pos =
+                            map.tile_to_world(challenged_at);
+                        if let Some(trail) =
+                            steps.trails.iter_mut().find(|trail| trail.0 == player_id)
+                        {
+                            let len = trail.2.len() - unplayed;
+                            trail.2.truncate(len);
+                        }
+
+                        let (event, awarded) = foul(
+                            fouler_id,
+                            fouler_team,
+                            challenged_at,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &mut cards,
+                        );
+                        if let MatchEvent::Foul {
+                            card: Some(card), ..
+                        } = event
+                        {
+                            if card.sends_off() {
+                                entities.delete(fouler_id).unwrap();
+                            }
+                        }
+                        events.events.push(event);
+                        restart.awarded = Some(awarded);
+                    }
+
+                    let player_tile_pos = tile_positions.get(player_id).unwrap().pos;
+                    let player_subtile_pos = sub_tile_positions.get(player_id).unwrap().pos;
+
+                    tile_positions.get_mut(ball_id).unwrap().pos = player_tile_pos;
+                    sub_tile_positions.get_mut(ball_id).unwrap().pos = player_subtile_pos;
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub struct PassSelectSystem;
 
 impl<'a> System<'a> for PassSelectSystem {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
     type SystemData = (
+        Entities<'a>,
         Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, PendingRestart>,
+        FetchMut<'a, MatchEvents>,
         FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
         WriteStorage<'a, CanMove>,
         ReadStorage<'a, Cursor>,
         ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Player>,
         WriteStorage<'a, Ball>,
+        ReadStorage<'a, Injury>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (input, mut turn, mut can_moves, cursors, tile_positions, mut balls) = data;
+        let (
+            entities,
+            input,
+            map,
+            rules,
+            restart,
+            mut events,
+            mut turn,
+            mut turn_log,
+            mut can_moves,
+            cursors,
+            tile_positions,
+            players,
+            mut balls,
+            injuries,
+        ) = data;
 
         if let TurnState::SelectPass { player_id, ball_id } = turn.state {
             // Find the ball
             let ball = balls.get_mut(ball_id).unwrap();
+            let player_tiles = sorted_player_tiles(
+                (&*entities, &players, &tile_positions)
+                    .join()
+                    .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                    .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                    .collect(),
+            );
 
             for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
-                if input.select {
+                if input.was_pressed(Action::Select) {
                     if cursor.state == CursorState::Still {
                         let at_end_of_path = {
                             can_moves.get(ball_id).unwrap().path.last() == Some(&cursor_pos.pos)
                         };
                         if at_end_of_path {
-                            turn.state = TurnState::Passing { player_id, ball_id };
-                            ball.state = BallState::Moving {
+                            let can_move = can_moves.remove(ball_id).unwrap();
+                            turn_log.actions.push(TurnAction::Pass {
+                                from: can_move.start,
+                                path: can_move.path.clone(),
+                            });
+                            turn.state = apply_pass(
                                 player_id,
-                                path: can_moves.get(ball_id).unwrap().path.clone(),
-                            };
-                            can_moves.remove(ball_id).unwrap();
+                                ball_id,
+                                ball,
+                                can_move.path,
+                                &map,
+                                &rules,
+                                &restart,
+                                &mut events,
+                                &player_tiles,
+                            );
                         }
                     }
-                } else if input.cancel {
+                } else if input.was_pressed(Action::Cancel) {
+                    can_moves.remove(ball_id).unwrap();
+                    turn.state = TurnState::SelectPlayer;
+                }
+            }
+        }
+    }
+}
+
+/// Picks the target of a lofted pass. Unlike ground passes there is no path,
+/// just any tile in range.
+pub struct LoftedPassSelectSystem;
+
+impl<'a> System<'a> for LoftedPassSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, PendingRestart>,
+        FetchMut<'a, MatchEvents>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        WriteStorage<'a, CanMove>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Ball>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            input,
+            map,
+            rules,
+            restart,
+            mut events,
+            mut rng,
+            mut turn,
+            mut turn_log,
+            mut can_moves,
+            cursors,
+            tile_positions,
+            attributes,
+            players,
+            mut balls,
+            injuries,
+        ) = data;
+
+        if let TurnState::SelectLoftedPass { player_id, ball_id } = turn.state {
+            let ball = balls.get_mut(ball_id).unwrap();
+            let player_tiles = sorted_player_tiles(
+                (&*entities, &players, &tile_positions)
+                    .join()
+                    .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                    .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                    .collect(),
+            );
+
+            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+                if input.was_pressed(Action::Select) {
+                    if cursor.state == CursorState::Still
+                        && can_moves
+                            .get(ball_id)
+                            .unwrap()
+                            .dests
+                            .contains(&cursor_pos.pos)
+                    {
+                        let can_move = can_moves.remove(ball_id).unwrap();
+                        let target = cursor_pos.pos;
+                        turn_log.actions.push(TurnAction::LoftedPass {
+                            from: can_move.start,
+                            target,
+                        });
+                        turn.state = apply_lofted_pass(
+                            player_id,
+                            ball_id,
+                            ball,
+                            can_move.start,
+                            target,
+                            attributes.get(player_id).unwrap().passing,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &restart,
+                            &mut events,
+                            &player_tiles,
+                        );
+                    }
+                } else if input.was_pressed(Action::Cancel) {
+                    can_moves.remove(ball_id).unwrap();
+                    turn.state = TurnState::SelectPlayer;
+                }
+            }
+        }
+    }
+}
+
+/// Picks where in the goal mouth to aim a shot.
+pub struct ShotSelectSystem;
+
+impl<'a> System<'a> for ShotSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        WriteStorage<'a, CanMove>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Attributes>,
+        WriteStorage<'a, Ball>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            input,
+            map,
+            rules,
+            mut rng,
+            mut turn,
+            mut turn_log,
+            mut can_moves,
+            cursors,
+            tile_positions,
+            players,
+            attributes,
+            mut balls,
+            injuries,
+        ) = data;
+
+        if let TurnState::SelectShot { player_id, ball_id } = turn.state {
+            let ball = balls.get_mut(ball_id).unwrap();
+            let player_tiles = sorted_player_tiles(
+                (&*entities, &players, &tile_positions)
+                    .join()
+                    .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                    .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                    .collect(),
+            );
+
+            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+                if input.was_pressed(Action::Select) {
+                    if cursor.state == CursorState::Still
+                        && can_moves
+                            .get(ball_id)
+                            .unwrap()
+                            .dests
+                            .contains(&cursor_pos.pos)
+                    {
+                        let can_move = can_moves.remove(ball_id).unwrap();
+                        let target = cursor_pos.pos;
+                        turn_log.actions.push(TurnAction::Shot {
+                            from: can_move.start,
+                            target,
+                        });
+                        turn.state = apply_shot(
+                            player_id,
+                            ball_id,
+                            ball,
+                            target,
+                            attributes.get(player_id).unwrap().shooting,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &player_tiles,
+                        );
+                    }
+                } else if input.was_pressed(Action::Cancel) {
                     can_moves.remove(ball_id).unwrap();
                     turn.state = TurnState::SelectPlayer;
                 }
@@ -609,27 +2588,103 @@ impl<'a> System<'a> for PassSelectSystem {
     }
 }
 
+/// A pass reaching the end of its path or flight.
+struct Arrival {
+    passer_id: Entity,
+    pos: Point2<u32>,
+    /// The way the ball was travelling, in tile space.
+    direction: Vector2<i32>,
+    power: f32,
+    lofted: bool,
+    shot: bool,
+}
+
 // TODO: this should probably be combined with PlayerMovementSystem
 pub struct BallMovementSystem;
 
 impl<'a> System<'a> for BallMovementSystem {
     #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
     type SystemData = (
+        Entities<'a>,
         Fetch<'a, DeltaTime>,
-        FetchMut<'a, Turn>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, Turn>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, MatchEvents>,
+        FetchMut<'a, Score>,
+        FetchMut<'a, Challenges>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Role>,
+        ReadStorage<'a, Attributes>,
         WriteStorage<'a, Ball>,
         WriteStorage<'a, TilePosition>,
         WriteStorage<'a, SubTilePosition>,
+        WriteStorage<'a, Elevation>,
+        ReadStorage<'a, Injury>,
     );
 
     fn run(&mut self, data: Self::SystemData) {
-        let (dt, mut turn, mut balls, mut tile_positions, mut sub_tile_positions) = data;
+        let (
+            entities,
+            dt,
+            map,
+            rules,
+            turn,
+            mut rng,
+            mut events,
+            mut score,
+            mut challenges,
+            players,
+            roles,
+            attributes,
+            mut balls,
+            mut tile_positions,
+            mut sub_tile_positions,
+            mut elevations,
+            injuries,
+        ) = data;
+
+        let player_tiles = sorted_player_tiles(
+            (&*entities, &players, &tile_positions)
+                .join()
+                .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                .collect(),
+        );
+        let keepers = goalkeepers(&player_tiles, &roles, &attributes);
+        // Goalkeepers who have thrown themselves across to a new tile
+        let mut dives = Vec::new();
 
-        for (ball, tile_position, sub_tile_position) in
-            (&mut balls, &mut tile_positions, &mut sub_tile_positions).join()
+        for (ball, tile_position, sub_tile_position, elevation) in (
+            &mut balls,
+            &mut tile_positions,
+            &mut sub_tile_positions,
+            &mut elevations,
+        )
+            .join()
         {
-            let mut finished_movement = false;
-            if let BallState::Moving { ref mut path, .. } = ball.state {
+            let mut arrived = None;
+            let mut saved = None;
+            if let Some(player) = ball.state.player_id().and_then(|id| players.get(id)) {
+                ball.last_touch = player.team;
+            }
+
+            if let BallState::Moving {
+                player_id,
+                ref mut path,
+                power,
+                shot,
+            } = ball.state
+            {
+                let passer_team = players.get(player_id).unwrap().team;
+                let speed = if shot {
+                    rules.shot_speed
+                } else {
+                    rules.pass_speed
+                };
+                let mut finished_movement = false;
+                let mut direction = Vector2::new(0, 0);
                 let mut remaining_dt = dt.dt;
 
                 while !finished_movement && remaining_dt > 0.0 {
@@ -637,10 +2692,10 @@ impl<'a> System<'a> for BallMovementSystem {
                         None => break,
                         Some(target) => *target,
                     };
-                    let disp = tile_to_subtile(target) - sub_tile_position.pos;
+                    let disp = map.tile_to_world(target) - sub_tile_position.pos;
 
                     if disp != Vector2::new(0.0, 0.0) {
-                        let velocity = disp.normalize() * PASS_SPEED;
+                        let velocity = velocity_towards(disp, map.tile_size(), speed);
 
                         let required_dt_x = required_time(disp.x, velocity.x);
                         let required_dt_y = required_time(disp.y, velocity.y);
@@ -653,21 +2708,1640 @@ impl<'a> System<'a> for BallMovementSystem {
                         remaining_dt -= remaining_dt_x.max(remaining_dt_y);
                     }
 
-                    if sub_tile_position.pos == tile_to_subtile(target) {
+                    if sub_tile_position.pos == map.tile_to_world(target) {
+                        direction = step_towards(tile_position.pos, target);
                         tile_position.pos = target;
                         path.remove(0);
+
+                        // Goalkeepers go for the ball where it passes
+                        // closest to them, unless it is already out of play
+                        let next = path.first().cloned();
+                        let diving = keepers.iter().find(|&&(pos, _, team, _)| {
+                            team != passer_team
+                                && map.in_field(target)
+                                && within_reach(pos, team, target, &map, &rules)
+                                && next.map_or(true, |next| {
+                                    tile_distance(pos, next) > tile_distance(pos, target)
+                                })
+                        });
+                        if !map.in_field(target) {
+                            path.clear();
+                        }
+                        if let Some(&(pos, keeper_id, _, handling)) = diving {
+                            let distance = tile_distance(pos, target);
+                            if rng.chance(save_chance(handling, distance, power, &rules)) {
+                                saved = Some((keeper_id, pos, handling, power, direction, shot));
+                                break;
+                            }
+                        }
                     }
 
                     if path.is_empty() {
                         finished_movement = true;
                     }
                 }
+
+                if finished_movement {
+                    arrived = Some(Arrival {
+                        passer_id: player_id,
+                        pos: tile_position.pos,
+                        direction,
+                        power,
+                        lofted: false,
+                        shot,
+                    });
+                }
             }
 
-            if finished_movement {
+            if let Some((keeper_id, keeper_pos, handling, power, direction, shot)) = saved {
+                if shot {
+                    let team = players.get(keeper_id).unwrap().team;
+                    report(MatchEvent::Saved { team }, &mut events, &mut score);
+                }
+
+                // The keeper dives across to the ball unless someone is in
+                // the way or they are already on the move
+                let to = tile_position.pos;
+                let blocked = player_tiles
+                    .iter()
+                    .any(|&(pos, player_id, _)| pos == to && player_id != keeper_id);
+                let still = match players.get(keeper_id).unwrap().state {
+                    PlayerState::Still => true,
+                    PlayerState::Running { .. } => false,
+                };
+                let hands = if blocked || !still {
+                    keeper_pos
+                } else {
+                    dives.push((keeper_id, to));
+                    to
+                };
+
+                // Held onto, or else pushed away to one side
                 ball.state = BallState::Free;
-                turn.state = TurnState::SelectPlayer;
+                if !rng.chance(first_touch_chance(handling, power, &rules)) {
+                    let side = if rng.chance(0.5) { 1 } else { -1 };
+                    ball.state = roll(
+                        keeper_id,
+                        to,
+                        Vector2::new(-direction.y * side, direction.x * side),
+                        rules.roll_speed * power.max(MIN_DEFLECTION_POWER),
+                        &map,
+                        &rules,
+                        &player_tiles,
+                    );
+                }
+                if ball.state == BallState::Free {
+                    tile_position.pos = hands;
+                    sub_tile_position.pos = map.tile_to_world(hands);
+                    ball.state = BallState::Possessed {
+                        player_id: keeper_id,
+                    };
+                }
+            }
+
+            if let BallState::Rolling {
+                ref mut path,
+                ref mut speed,
+                ..
+            } = ball.state
+            {
+                let mut remaining_dt = dt.dt;
+
+                while !path.is_empty() && remaining_dt > 0.0 {
+                    let target = path[0];
+                    let disp = map.tile_to_world(target) - sub_tile_position.pos;
+
+                    if disp != Vector2::new(0.0, 0.0) {
+                        let velocity = velocity_towards(disp, map.tile_size(), *speed);
+
+                        let required_dt_x = required_time(disp.x, velocity.x);
+                        let required_dt_y = required_time(disp.y, velocity.y);
+
+                        let remaining_dt_x = remaining_dt.min(required_dt_x);
+                        let remaining_dt_y = remaining_dt.min(required_dt_y);
+
+                        sub_tile_position.pos +=
+                            Vector2::new(velocity.x * remaining_dt_x, velocity.y * remaining_dt_y);
+                        let step_dt = remaining_dt_x.max(remaining_dt_y);
+                        remaining_dt -= step_dt;
+                        *speed = (*speed - rules.roll_deceleration * step_dt).max(MIN_ROLL_SPEED);
+                    }
+
+                    if sub_tile_position.pos == map.tile_to_world(target) {
+                        tile_position.pos = target;
+                        path.remove(0);
+                    }
+                }
+            }
+            let stopped_rolling = match ball.state {
+                BallState::Rolling { ref path, .. } => path.is_empty(),
+                _ => false,
+            };
+            if stopped_rolling {
+                ball.state = settle(tile_position.pos, &player_tiles);
+            }
+
+            if let BallState::Lofted {
+                player_id,
+                from,
+                to,
+                ref mut elapsed,
+            } = ball.state
+            {
+                // Fly in a straight line over everyone's heads
+                let distance = tile_distance(from, to);
+                let duration = distance / rules.pass_speed;
+                *elapsed = (*elapsed + dt.dt).min(duration);
+                let t = if duration > 0.0 {
+                    *elapsed / duration
+                } else {
+                    1.0
+                };
+
+                let start = map.tile_to_world(from);
+                sub_tile_position.pos = start + (map.tile_to_world(to) - start) * t;
+                elevation.height =
+                    (t * PI).sin() * distance * LOFTED_PASS_HEIGHT * map.tile_size().y;
+
+                if t >= 1.0 {
+                    arrived = Some(Arrival {
+                        passer_id: player_id,
+                        pos: to,
+                        direction: step_towards(from, to),
+                        power: pass_power(distance, rules.lofted_pass_distance),
+                        lofted: true,
+                        shot: false,
+                    });
+                }
+            }
+
+            if let Some(arrival) = arrived {
+                let (passer_id, to, power) = (arrival.passer_id, arrival.pos, arrival.power);
+                tile_position.pos = to;
+                elevation.height = 0.0;
+                ball.state = BallState::Free;
+
+                let passer_team = players.get(passer_id).unwrap().team;
+                let receiver = player_tiles
+                    .iter()
+                    .find(|&&(pos, _, _)| pos == to)
+                    .map(|&(_, player_id, team)| (player_id, team));
+                match receiver {
+                    // Nobody can play it once it is out
+                    _ if !map.in_field(to) => {}
+                    Some((blocker_id, team)) if arrival.shot => {
+                        // Shots go wherever they bounce off whoever is in
+                        // the way
+                        report(MatchEvent::Blocked { team }, &mut events, &mut score);
+                        let free_tiles: Vec<Point2<u32>> = get_adjacent_tiles(to, map.size())
+                            .into_iter()
+                            .filter(|tile| !player_tiles.iter().any(|&(pos, _, _)| pos == *tile))
+                            .collect();
+                        if !free_tiles.is_empty() {
+                            let bounce = rng.range(0, free_tiles.len() as i32 - 1) as usize;
+                            ball.state = roll(
+                                blocker_id,
+                                to,
+                                step_towards(to, free_tiles[bounce]),
+                                rules.roll_speed * power,
+                                &map,
+                                &rules,
+                                &player_tiles,
+                            );
+                        }
+                        if ball.state == BallState::Free {
+                            // Nowhere to go, so it drops to the blocker
+                            ball.state = BallState::Possessed {
+                                player_id: blocker_id,
+                            };
+                        }
+                    }
+                    Some((receiver_id, team)) if team == passer_team => {
+                        // The receiver either traps the ball or it bounces
+                        // off them towards a free tile. Goalkeepers can use
+                        // their hands in their own area.
+                        let receiver_attributes = attributes.get(receiver_id).unwrap();
+                        let control = if is_handling(receiver_id, to, team, &roles, &map) {
+                            receiver_attributes
+                                .control
+                                .max(receiver_attributes.handling)
+                        } else {
+                            receiver_attributes.control
+                        };
+                        let free_tiles: Vec<Point2<u32>> = get_adjacent_tiles(to, map.size())
+                            .into_iter()
+                            .filter(|tile| !player_tiles.iter().any(|&(pos, _, _)| pos == *tile))
+                            .collect();
+                        if rng.chance(first_touch_chance(control, power, &rules))
+                            || free_tiles.is_empty()
+                        {
+                            ball.state = BallState::Possessed {
+                                player_id: receiver_id,
+                            };
+                        } else {
+                            let bounce = rng.range(0, free_tiles.len() as i32 - 1) as usize;
+                            let speed = rules.roll_speed * power.max(MIN_DEFLECTION_POWER);
+                            ball.state = roll(
+                                receiver_id,
+                                to,
+                                step_towards(to, free_tiles[bounce]),
+                                speed,
+                                &map,
+                                &rules,
+                                &player_tiles,
+                            );
+                            if ball.state == BallState::Free {
+                                // Too soft to get away from the receiver
+                                ball.state = BallState::Possessed {
+                                    player_id: receiver_id,
+                                };
+                            }
+                        }
+                    }
+                    // Opponents pick it up as they would any loose ball
+                    Some(_) => {}
+                    None => {
+                        if arrival.lofted {
+                            // Goalkeepers come for balls dropping into their
+                            // area before anyone else can get to them
+                            let claim = keepers.iter().find(|&&(pos, _, team, _)| {
+                                team != passer_team && within_reach(pos, team, to, &map, &rules)
+                            });
+                            if let Some(&(pos, keeper_id, _, handling)) = claim {
+                                let distance = tile_distance(pos, to);
+                                if rng.chance(save_chance(handling, distance, power, &rules)) {
+                                    tile_position.pos = pos;
+                                    sub_tile_position.pos = map.tile_to_world(pos);
+                                    ball.state = BallState::Possessed {
+                                        player_id: keeper_id,
+                                    };
+                                }
+                            }
+                        }
+                        if arrival.lofted && ball.state == BallState::Free {
+                            // Opponents can only challenge for a lofted ball
+                            // where it comes down
+                            let challengers = player_tiles.iter().filter(|&&(pos, _, team)| {
+                                team != passer_team && is_adjacent(pos, to)
+                            });
+                            for &(pos, player_id, _) in challengers {
+                                challenges.players.push((player_id, false));
+                                if rng.chance(rules.lofted_pass_intercept_chance) {
+                                    tile_position.pos = pos;
+                                    sub_tile_position.pos = map.tile_to_world(pos);
+                                    ball.state = BallState::Possessed { player_id };
+                                    break;
+                                }
+                            }
+                        }
+
+                        // Passes into space run on, lofted ones less so as
+                        // they have to bounce first. Through-balls and
+                        // knock-ons wait where they were aimed for the
+                        // runner.
+                        let run_onto = match turn.state {
+                            TurnState::ThroughBall { .. } | TurnState::KnockOn { .. } => true,
+                            _ => false,
+                        };
+                        if ball.state == BallState::Free && !run_onto {
+                            let mut speed = rules.roll_speed * power;
+                            if arrival.lofted {
+                                speed /= 2.0;
+                            }
+                            ball.state = roll(
+                                passer_id,
+                                to,
+                                arrival.direction,
+                                speed,
+                                &map,
+                                &rules,
+                                &player_tiles,
+                            );
+                        }
+                    }
+                }
+            }
+
+            if let Some(player) = ball.state.player_id().and_then(|id| players.get(id)) {
+                ball.last_touch = player.team;
+            }
+        }
+
+        for (keeper_id, to) in dives {
+            tile_positions.get_mut(keeper_id).unwrap().pos = to;
+            sub_tile_positions.get_mut(keeper_id).unwrap().pos = map.tile_to_world(to);
+        }
+    }
+}
+
+/// Picks where to play a through-ball and then the teammate to run onto it.
+pub struct ThroughBallSelectSystem;
+
+impl<'a> System<'a> for ThroughBallSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        WriteStorage<'a, CanMove>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Ball>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            input,
+            map,
+            rules,
+            mut rng,
+            mut turn,
+            mut turn_log,
+            mut can_moves,
+            cursors,
+            tile_positions,
+            mut players,
+            mut balls,
+            injuries,
+        ) = data;
+
+        let player_tiles = sorted_player_tiles(
+            (&*entities, &players, &tile_positions)
+                .join()
+                .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                .collect(),
+        );
+        let occupied: Vec<Point2<u32>> = player_tiles.iter().map(|&(pos, _, _)| pos).collect();
+
+        if let TurnState::SelectThroughBall { player_id, ball_id } = turn.state {
+            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+                if input.was_pressed(Action::Select) {
+                    if cursor.state == CursorState::Still {
+                        let can_move = can_moves.get_mut(ball_id).unwrap();
+                        let target = cursor_pos.pos;
+                        if can_move.path.last() == Some(&target) && can_move.dests.contains(&target)
+                        {
+                            // Offer the teammates who can get there in time
+                            let passer_team = players.get(player_id).unwrap().team;
+                            let costs = run_step_costs(passer_team, &map, &rules, &player_tiles);
+                            let receivers: Vec<Point2<u32>> = player_tiles
+                                .iter()
+                                .filter(|&&(pos, receiver_id, team)| {
+                                    team == passer_team
+                                        && receiver_id != player_id
+                                        && find_run_path(
+                                            pos, target, &map, &rules, &costs, &occupied,
+                                        )
+                                        .is_some()
+                                })
+                                .map(|&(pos, _, _)| pos)
+                                .collect();
+                            if !receivers.is_empty() {
+                                can_move.dests = receivers;
+                                turn.state = TurnState::SelectReceiver { player_id, ball_id };
+                            }
+                        }
+                    }
+                } else if input.was_pressed(Action::Cancel) {
+                    can_moves.remove(ball_id).unwrap();
+                    turn.state = TurnState::SelectPlayer;
+                }
+            }
+        } else if let TurnState::SelectReceiver { player_id, ball_id } = turn.state {
+            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+                if input.was_pressed(Action::Select) {
+                    let is_receiver = can_moves
+                        .get(ball_id)
+                        .unwrap()
+                        .dests
+                        .contains(&cursor_pos.pos);
+                    if cursor.state == CursorState::Still && is_receiver {
+                        let receiver_id = player_tiles
+                            .iter()
+                            .find(|&&(pos, _, _)| pos == cursor_pos.pos)
+                            .map(|&(_, receiver_id, _)| receiver_id)
+                            .unwrap();
+                        let can_move = can_moves.remove(ball_id).unwrap();
+                        let action = TurnAction::ThroughBall {
+                            from: can_move.start,
+                            path: can_move.path.clone(),
+                            receiver: cursor_pos.pos,
+                        };
+                        let state = apply_through_ball(
+                            player_id,
+                            ball_id,
+                            balls.get_mut(ball_id).unwrap(),
+                            receiver_id,
+                            can_move.path,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &player_tiles,
+                            &mut players,
+                        );
+                        // Receivers were only offered if they could get there
+                        if let Some(state) = state {
+                            turn_log.actions.push(action);
+                            turn.state = state;
+                        }
+                    }
+                } else if input.was_pressed(Action::Cancel) {
+                    can_moves.remove(ball_id).unwrap();
+                    turn.state = TurnState::SelectPlayer;
+                }
+            }
+        }
+    }
+}
+
+/// Picks where a player sprints to after knocking the ball on ahead of them.
+pub struct KnockOnSelectSystem;
+
+impl<'a> System<'a> for KnockOnSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        WriteStorage<'a, CanMove>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Ball>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            input,
+            map,
+            rules,
+            mut rng,
+            mut turn,
+            mut turn_log,
+            mut can_moves,
+            cursors,
+            tile_positions,
+            mut players,
+            mut balls,
+            injuries,
+        ) = data;
+
+        if let TurnState::SelectKnockOn { player_id, ball_id } = turn.state {
+            let player_tiles = sorted_player_tiles(
+                (&*entities, &players, &tile_positions)
+                    .join()
+                    .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                    .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                    .collect(),
+            );
+
+            for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+                if input.was_pressed(Action::Select) {
+                    if cursor.state == CursorState::Still {
+                        let at_end_of_path = {
+                            can_moves.get(player_id).unwrap().path.last() == Some(&cursor_pos.pos)
+                        };
+                        if at_end_of_path {
+                            let can_move = can_moves.remove(player_id).unwrap();
+                            turn_log.actions.push(TurnAction::KnockOn {
+                                from: can_move.start,
+                                path: can_move.path.clone(),
+                            });
+                            turn.state = apply_knock_on(
+                                player_id,
+                                ball_id,
+                                balls.get_mut(ball_id).unwrap(),
+                                can_move.path,
+                                &map,
+                                &rules,
+                                &mut rng,
+                                &player_tiles,
+                                &mut players,
+                            );
+                        }
+                    }
+                } else if input.was_pressed(Action::Cancel) {
+                    can_moves.remove(player_id).unwrap();
+                    turn.state = TurnState::SelectPlayer;
+                }
             }
         }
     }
 }
+
+/// Works out who gets hurt in the challenges and collisions of the frame. A
+/// knock weakens a player for the rest of the match, while a serious injury
+/// means they have to be replaced, or leave their team a player down if it
+/// has no substitutions left.
+pub struct InjurySystem;
+
+impl<'a> System<'a> for InjurySystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, Substitutions>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Challenges>,
+        FetchMut<'a, PendingRestart>,
+        FetchMut<'a, MatchEvents>,
+        WriteStorage<'a, Player>,
+        ReadStorage<'a, Bench>,
+        WriteStorage<'a, OffPitch>,
+        WriteStorage<'a, Injury>,
+        WriteStorage<'a, Attributes>,
+        WriteStorage<'a, Formation>,
+        WriteStorage<'a, Ball>,
+        WriteStorage<'a, TilePosition>,
+        WriteStorage<'a, SubTilePosition>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            rules,
+            subs,
+            mut rng,
+            mut challenges,
+            mut pending,
+            mut events,
+            mut players,
+            benched,
+            mut off_pitch,
+            mut injuries,
+            mut attributes,
+            mut formations,
+            mut balls,
+            mut tile_positions,
+            mut sub_tile_positions,
+        ) = data;
+
+        for (player_id, fouled) in challenges.players.drain(..) {
+            let chance = if fouled {
+                rules.foul_injury_chance
+            } else {
+                rules.injury_chance
+            };
+            // Anyone already off the pitch or unable to play on has nothing
+            // more to lose
+            let out = tile_positions.get(player_id).is_none()
+                || injuries
+                    .get(player_id)
+                    .map_or(false, |injury| injury.serious);
+            if !rng.chance(chance) || out {
+                continue;
+            }
+            let team = players.get(player_id).unwrap().team;
+
+            if !rng.chance(rules.serious_injury_chance) {
+                let weaken = |skill: u32| skill.saturating_sub(rules.injury_attribute_loss).max(1);
+                let skills = attributes.get_mut(player_id).unwrap();
+                skills.passing = weaken(skills.passing);
+                skills.shooting = weaken(skills.shooting);
+                skills.control = weaken(skills.control);
+                skills.handling = weaken(skills.handling);
+                if injuries.get(player_id).is_none() {
+                    injuries.insert(player_id, Injury { serious: false });
+                }
+                events.events.push(MatchEvent::Injury {
+                    team,
+                    effect: InjuryEffect::Knock,
+                });
+                continue;
+            }
+
+            // Anything they were doing stops where they are, and they take
+            // no further part in play even while waiting to be replaced
+            injuries.insert(player_id, Injury { serious: true });
+            players.get_mut(player_id).unwrap().state = PlayerState::Still;
+            let pos = tile_positions.get(player_id).unwrap().pos;
+            sub_tile_positions.get_mut(player_id).unwrap().pos = map.tile_to_world(pos);
+            for ball in (&mut balls).join() {
+                if ball.state == (BallState::Possessed { player_id }) {
+                    ball.state = BallState::Free;
+                }
+            }
+            if pending
+                .taker
+                .map_or(false, |(taker_id, _)| taker_id == player_id)
+            {
+                pending.taker = None;
+            }
+
+            let bench: Vec<_> = (&players, &benched)
+                .join()
+                .map(|(reserve, _)| reserve.team)
+                .collect();
+            if can_substitute(team, &bench, &subs, &rules) {
+                events.events.push(MatchEvent::Injury {
+                    team,
+                    effect: InjuryEffect::Substitution,
+                });
+                continue;
+            }
+
+            // With nobody to replace them the team is down to one fewer
+            off_pitch.insert(player_id, OffPitch);
+            tile_positions.remove(player_id);
+            sub_tile_positions.remove(player_id);
+            formations.remove(player_id);
+            events.events.push(MatchEvent::Injury {
+                team,
+                effect: InjuryEffect::Off,
+            });
+        }
+    }
+}
+
+/// Hands control back once everything set in motion by an activation has
+/// come to rest.
+pub struct ActivationEndSystem;
+
+impl<'a> System<'a> for ActivationEndSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, PendingRestart>,
+        FetchMut<'a, MatchClock>,
+        FetchMut<'a, MatchEvents>,
+        FetchMut<'a, Score>,
+        FetchMut<'a, Steps>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, OffPitch>,
+        ReadStorage<'a, Ball>,
+        ReadStorage<'a, TilePosition>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            map,
+            rules,
+            mut turn,
+            mut restart,
+            mut clock,
+            mut events,
+            mut score,
+            mut steps,
+            players,
+            off_pitch,
+            balls,
+            tile_positions,
+        ) = data;
+
+        let players_moving = (&players, !&off_pitch)
+            .join()
+            .any(|(player, _)| match player.state {
+                PlayerState::Running { .. } => true,
+                PlayerState::Still => false,
+            });
+        let balls_moving = balls.join().any(|ball| match ball.state {
+            BallState::Moving { .. } | BallState::Lofted { .. } | BallState::Rolling { .. } => true,
+            BallState::Free | BallState::Possessed { .. } => false,
+        });
+
+        if !players_moving && !balls_moving {
+            turn.state = TurnState::SelectPlayer;
+            *steps = Steps::default();
+
+            // A restart has been taken once the ball leaves the taker
+            if let Some((taker_id, _)) = restart.taker {
+                let kept = balls.join().any(|ball| {
+                    ball.state
+                        == BallState::Possessed {
+                            player_id: taker_id,
+                        }
+                });
+                if !kept {
+                    restart.taker = None;
+                }
+            }
+            if let Some(awarded) = restart.awarded.take() {
+                turn.state = TurnState::Restart { restart: awarded };
+            }
+
+            for (ball, pos) in (&balls, &tile_positions).join() {
+                if ball.state != BallState::Free || map.in_field(pos.pos) {
+                    continue;
+                }
+                let (event, out_restart) = out_of_play(pos.pos, ball.last_touch, &map);
+                report(event, &mut events, &mut score);
+                turn.state = if out_restart.kind == RestartKind::KickOff {
+                    TurnState::KickOff {
+                        team: out_restart.team,
+                    }
+                } else {
+                    TurnState::Restart {
+                        restart: out_restart,
+                    }
+                };
+            }
+
+            // A restart which is due, such as a free kick or a penalty, is
+            // still taken once time is up, and the half ends after it. There
+            // is no kick-off after a goal though, as the next half starts
+            // with its own.
+            clock.elapsed += 1;
+            let length = if clock.in_extra_time() {
+                rules.extra_time_length
+            } else {
+                rules.half_length
+            };
+            let restart_due = restart.taker.is_some()
+                || match turn.state {
+                    TurnState::Restart { .. } => true,
+                    _ => false,
+                };
+            if clock.elapsed >= length && !restart_due {
+                let level = score.red == score.blue;
+                let extra_time = rules.knockout && level && rules.extra_time_length > 0;
+                let next_half = match clock.half {
+                    1 | 3 => true,
+                    2 => extra_time,
+                    _ => false,
+                };
+                if next_half {
+                    if clock.half == 2 {
+                        events.events.push(MatchEvent::ExtraTime);
+                    }
+                    clock.half += 1;
+                    clock.elapsed = 0;
+                    turn.state = TurnState::KickOff {
+                        team: clock.kick_off_team(),
+                    };
+                } else if rules.knockout && level {
+                    turn.state = TurnState::Shootout;
+                } else {
+                    events.events.push(MatchEvent::FullTime);
+                    turn.state = TurnState::FullTime;
+                }
+            }
+        }
+    }
+}
+
+/// Lines both teams up in their own halves from their formations, then has
+/// the kick-off set up like any other restart.
+pub struct KickOffSystem;
+
+impl<'a> System<'a> for KickOffSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Fetch<'a, Map>,
+        FetchMut<'a, Turn>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Formation>,
+        WriteStorage<'a, TilePosition>,
+        WriteStorage<'a, SubTilePosition>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (map, mut turn, players, formations, mut tile_positions, mut sub_tile_positions) = data;
+
+        let team = match turn.state {
+            TurnState::KickOff { team } => team,
+            _ => return,
+        };
+
+        for (player, formation, tile_position, sub_tile_position) in (
+            &players,
+            &formations,
+            &mut tile_positions,
+            &mut sub_tile_positions,
+        )
+            .join()
+        {
+            tile_position.pos = map.formation_tile(player.team, *formation);
+            sub_tile_position.pos = map.tile_to_world(tile_position.pos);
+        }
+
+        turn.state = TurnState::Restart {
+            restart: Restart {
+                kind: RestartKind::KickOff,
+                team,
+                spot: map.centre_spot(),
+            },
+        };
+    }
+}
+
+/// Puts the ball and players in place for a restart. The nearest player on
+/// the team taking it gets the ball, or their goalkeeper for a goal kick, and
+/// anyone else in the way is moved aside.
+pub struct RestartSystem;
+
+impl<'a> System<'a> for RestartSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, PendingRestart>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Role>,
+        WriteStorage<'a, Ball>,
+        WriteStorage<'a, TilePosition>,
+        WriteStorage<'a, SubTilePosition>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            rules,
+            mut turn,
+            mut pending,
+            players,
+            roles,
+            mut balls,
+            mut tile_positions,
+            mut sub_tile_positions,
+            injuries,
+        ) = data;
+
+        let restart = match turn.state {
+            TurnState::Restart { restart } => restart,
+            _ => return,
+        };
+
+        let player_tiles = sorted_player_tiles(
+            (&*entities, &players, &tile_positions)
+                .join()
+                .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                .collect(),
+        );
+        let prefers = |player_id: Entity| {
+            restart.kind != RestartKind::GoalKick || roles.get(player_id) == Some(&Role::Goalkeeper)
+        };
+        let taker = player_tiles
+            .iter()
+            .filter(|&&(_, _, team)| team == restart.team)
+            .min_by(|&&(a_pos, a_id, _), &&(b_pos, b_id, _)| {
+                let a = (!prefers(a_id), tile_distance(a_pos, restart.spot));
+                let b = (!prefers(b_id), tile_distance(b_pos, restart.spot));
+                a.partial_cmp(&b).unwrap()
+            });
+        let taker_id = match taker {
+            Some(&(_, taker_id, _)) => taker_id,
+            None => {
+                turn.state = TurnState::SelectPlayer;
+                return;
+            }
+        };
+
+        // Opponents back off to the restart distance, and nobody else can
+        // stand on the spot. At a penalty only the goalkeeper can stay in
+        // the area.
+        let outside = if restart.kind == RestartKind::Penalty {
+            Some(restart.team.opponents())
+        } else {
+            None
+        };
+        let mut occupied: Vec<Point2<u32>> = player_tiles
+            .iter()
+            .filter(|&&(_, player_id, _)| player_id != taker_id)
+            .map(|&(pos, _, _)| pos)
+            .collect();
+        occupied.push(restart.spot);
+        for &(pos, player_id, team) in &player_tiles {
+            let keeper = roles.get(player_id) == Some(&Role::Goalkeeper);
+            if player_id == taker_id || (outside == Some(team) && keeper) {
+                continue;
+            }
+            let distance = if team != restart.team {
+                rules.restart_distance as f32
+            } else {
+                1.0
+            };
+            let in_area = outside.map_or(false, |area| map.in_penalty_area(area, pos));
+            if tile_distance(pos, restart.spot) >= distance && !in_area {
+                continue;
+            }
+            let to = clear_tile(pos, restart.spot, distance, outside, &map, &occupied);
+            occupied.retain(|&tile| tile != pos);
+            occupied.push(to);
+            tile_positions.get_mut(player_id).unwrap().pos = to;
+            sub_tile_positions.get_mut(player_id).unwrap().pos = map.tile_to_world(to);
+        }
+
+        tile_positions.get_mut(taker_id).unwrap().pos = restart.spot;
+        sub_tile_positions.get_mut(taker_id).unwrap().pos = map.tile_to_world(restart.spot);
+        for (ball_id, ball) in (&*entities, &mut balls).join() {
+            ball.state = BallState::Possessed {
+                player_id: taker_id,
+            };
+            ball.last_touch = restart.team;
+            tile_positions.get_mut(ball_id).unwrap().pos = restart.spot;
+            sub_tile_positions.get_mut(ball_id).unwrap().pos = map.tile_to_world(restart.spot);
+        }
+
+        pending.taker = Some((taker_id, restart.kind));
+        turn.state = TurnState::SelectPlayer;
+    }
+}
+
+/// Picks which of the bench, in the order they are listed, comes on for a
+/// player.
+pub struct SubstituteSelectSystem;
+
+impl<'a> System<'a> for SubstituteSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Input>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Bench>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (entities, input, mut turn, mut turn_log, tile_positions, players, benched) = data;
+
+        if let TurnState::SelectSubstitute {
+            player_id,
+            mut selected,
+        } = turn.state
+        {
+            let team = players.get(player_id).unwrap().team;
+            let bench = sorted_bench(
+                (&*entities, &players, &benched)
+                    .join()
+                    .filter(|&(_, other, _)| other.team == team)
+                    .map(|(reserve_id, _, _)| reserve_id)
+                    .collect(),
+            );
+
+            if input.was_pressed(Action::CursorUp) && selected > 0 {
+                selected -= 1;
+            }
+            if input.was_pressed(Action::CursorDown) && selected + 1 < bench.len() {
+                selected += 1;
+            }
+            turn.state = TurnState::SelectSubstitute {
+                player_id,
+                selected,
+            };
+
+            if input.was_pressed(Action::Select) {
+                if let Some(state) = apply_substitute(player_id, selected, &bench) {
+                    turn_log.actions.push(TurnAction::Substitute {
+                        from: tile_positions.get(player_id).unwrap().pos,
+                        reserve: selected,
+                    });
+                    turn.state = state;
+                }
+            } else if input.was_pressed(Action::Cancel) {
+                turn.state = TurnState::SelectPlayer;
+            }
+        }
+    }
+}
+
+/// Brings a reserve on in place of the player they replace, who leaves the
+/// pitch for good.
+pub struct SubstitutionSystem;
+
+impl<'a> System<'a> for SubstitutionSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        FetchMut<'a, Turn>,
+        FetchMut<'a, PendingRestart>,
+        FetchMut<'a, Substitutions>,
+        FetchMut<'a, MatchEvents>,
+        ReadStorage<'a, Player>,
+        WriteStorage<'a, Bench>,
+        WriteStorage<'a, OffPitch>,
+        WriteStorage<'a, Formation>,
+        WriteStorage<'a, Ball>,
+        WriteStorage<'a, TilePosition>,
+        WriteStorage<'a, SubTilePosition>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            mut turn,
+            mut pending,
+            mut subs,
+            mut events,
+            players,
+            mut benched,
+            mut off_pitch,
+            mut formations,
+            mut balls,
+            mut tile_positions,
+            mut sub_tile_positions,
+        ) = data;
+
+        if let TurnState::Substitution {
+            player_id,
+            reserve_id,
+        } = turn.state
+        {
+            // The reserve comes on exactly where the player goes off, but
+            // fresh and without their bookings
+            let tile_pos = tile_positions.remove(player_id).unwrap();
+            let sub_tile_pos = sub_tile_positions.remove(player_id).unwrap();
+            tile_positions.insert(reserve_id, tile_pos);
+            sub_tile_positions.insert(reserve_id, sub_tile_pos);
+            if let Some(formation) = formations.remove(player_id) {
+                formations.insert(reserve_id, formation);
+            }
+            benched.remove(reserve_id);
+            off_pitch.insert(player_id, OffPitch);
+
+            for ball in (&mut balls).join() {
+                if ball.state == (BallState::Possessed { player_id }) {
+                    ball.state = BallState::Possessed {
+                        player_id: reserve_id,
+                    };
+                }
+            }
+            if let Some((taker_id, kind)) = pending.taker {
+                if taker_id == player_id {
+                    pending.taker = Some((reserve_id, kind));
+                }
+            }
+
+            let team = players.get(player_id).unwrap().team;
+            subs.add(team);
+            events.events.push(MatchEvent::Substitution { team });
+            turn.state = TurnState::SelectPlayer;
+        }
+    }
+}
+
+pub struct ShootoutSelectSystem;
+
+impl<'a> System<'a> for ShootoutSelectSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Input>,
+        Fetch<'a, Map>,
+        FetchMut<'a, Shootout>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        WriteStorage<'a, CanMove>,
+        ReadStorage<'a, Cursor>,
+        ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Player>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            input,
+            map,
+            mut shootout,
+            mut turn,
+            mut turn_log,
+            mut can_moves,
+            cursors,
+            tile_positions,
+            players,
+        ) = data;
+
+        // The side in goal reveal their dive once the kick has been aimed,
+        // which only they can do as nobody else knows it
+        if let TurnState::ShootoutReveal { taker_id, target } = turn.state {
+            if let Some(dive) = shootout.dive {
+                let salt = shootout.salt;
+                turn_log
+                    .actions
+                    .push(TurnAction::ShootoutReveal { dive, salt });
+                if let Some(state) =
+                    apply_shootout_reveal(taker_id, target, dive, salt, &mut shootout)
+                {
+                    turn.state = state;
+                }
+            }
+            return;
+        }
+
+        for (cursor, cursor_pos) in (&cursors, &tile_positions).join() {
+            if cursor.state != CursorState::Still {
+                continue;
+            }
+
+            match turn.state {
+                TurnState::ShootoutDive { team } => {
+                    let dests = map.goal_mouth(PlayerTeam::Blue);
+                    if input.was_pressed(Action::Select) && dests.contains(&cursor_pos.pos) {
+                        // The salt comes from outside the game, so that the
+                        // other side can't work it out and try each dive
+                        let dive = cursor_pos.pos;
+                        let salt = RandomState::new().build_hasher().finish();
+                        shootout.dive = Some(dive);
+                        shootout.salt = salt;
+                        let commitment = turn_file::dive_commitment(dive, salt);
+                        turn_log
+                            .actions
+                            .push(TurnAction::ShootoutDive { commitment });
+                        turn.state = apply_shootout_dive(team, commitment, &mut shootout);
+                    }
+                }
+                TurnState::ShootoutTaker { team } => {
+                    if !input.was_pressed(Action::Select) {
+                        continue;
+                    }
+                    // Everyone has to take a kick before anyone takes two
+                    let taker = (&*entities, &players, &tile_positions)
+                        .join()
+                        .find(|&(player_id, player, pos)| {
+                            pos.pos == cursor_pos.pos
+                                && player.team == team
+                                && !shootout.taken.contains(&player_id)
+                        })
+                        .map(|(player_id, _, _)| player_id);
+                    if let Some(taker_id) = taker {
+                        let can_move = CanMove {
+                            start: map.penalty_spot(PlayerTeam::Blue),
+                            distance: 0,
+                            costs: StepCosts::default(),
+                            dests: map.goal_mouth(PlayerTeam::Blue),
+                            path: Vec::new(),
+                        };
+                        can_moves.insert(taker_id, can_move);
+                        turn.state = TurnState::ShootoutShot { taker_id };
+                    }
+                }
+                TurnState::ShootoutShot { taker_id } => {
+                    let dests = &can_moves.get(taker_id).unwrap().dests;
+                    if input.was_pressed(Action::Select) && dests.contains(&cursor_pos.pos) {
+                        can_moves.remove(taker_id).unwrap();
+                        let target = cursor_pos.pos;
+                        turn_log.actions.push(TurnAction::ShootoutKick {
+                            from: tile_positions.get(taker_id).unwrap().pos,
+                            target,
+                        });
+                        turn.state = apply_shootout_kick(taker_id, target);
+                    } else if input.was_pressed(Action::Cancel) {
+                        can_moves.remove(taker_id).unwrap();
+                        turn.state = TurnState::ShootoutTaker {
+                            team: players.get(taker_id).unwrap().team,
+                        };
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+pub struct ShootoutSystem;
+
+impl<'a> System<'a> for ShootoutSystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, PendingRestart>,
+        FetchMut<'a, Shootout>,
+        FetchMut<'a, MatchEvents>,
+        ReadStorage<'a, Player>,
+        ReadStorage<'a, Role>,
+        ReadStorage<'a, Attributes>,
+        WriteStorage<'a, Ball>,
+        WriteStorage<'a, TilePosition>,
+        WriteStorage<'a, SubTilePosition>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            rules,
+            mut rng,
+            mut turn,
+            mut pending,
+            mut shootout,
+            mut events,
+            players,
+            roles,
+            attributes,
+            mut balls,
+            mut tile_positions,
+            mut sub_tile_positions,
+            injuries,
+        ) = data;
+
+        match turn.state {
+            TurnState::Shootout => {
+                // The ball sits on the spot for every kick
+                let spot = map.penalty_spot(PlayerTeam::Blue);
+                for (ball, pos, sub_pos) in
+                    (&mut balls, &mut tile_positions, &mut sub_tile_positions).join()
+                {
+                    ball.state = BallState::Free;
+                    pos.pos = spot;
+                    sub_pos.pos = map.tile_to_world(spot);
+                }
+                pending.taker = None;
+                shootout.started = true;
+                turn.state = TurnState::ShootoutDive {
+                    team: shootout.next_team().opponents(),
+                };
+            }
+            TurnState::PenaltyKick { taker_id, target } => {
+                let team = players.get(taker_id).unwrap().team;
+
+                // The taker steps up to the spot, swapping places with
+                // whoever was standing on it
+                let spot = map.penalty_spot(PlayerTeam::Blue);
+                let from = tile_positions.get(taker_id).unwrap().pos;
+                let on_spot = (&*entities, &players, &tile_positions)
+                    .join()
+                    .find(|&(player_id, _, pos)| pos.pos == spot && player_id != taker_id)
+                    .map(|(player_id, _, _)| player_id);
+                if let Some(player_id) = on_spot {
+                    tile_positions.get_mut(player_id).unwrap().pos = from;
+                    sub_tile_positions.get_mut(player_id).unwrap().pos = map.tile_to_world(from);
+                }
+                tile_positions.get_mut(taker_id).unwrap().pos = spot;
+                sub_tile_positions.get_mut(taker_id).unwrap().pos = map.tile_to_world(spot);
+
+                let player_tiles = sorted_player_tiles(
+                    (&*entities, &players, &tile_positions)
+                        .join()
+                        .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                        .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                        .collect(),
+                );
+                let keeper = goalkeepers(&player_tiles, &roles, &attributes)
+                    .into_iter()
+                    .find(|&(_, _, keeper_team, _)| keeper_team != team);
+
+                // Shots off target miss, and the goalkeeper might get to any
+                // within reach of where they dived
+                let shooting = attributes.get(taker_id).unwrap().shooting;
+                let landing = aim(spot, target, shooting, 0, &map, &rules, &mut rng);
+                let on_target = map.goal_mouth(PlayerTeam::Blue).contains(&landing);
+                let saved = match (keeper, shootout.dive.take()) {
+                    (Some((_, _, _, handling)), Some(dive)) => {
+                        let distance = tile_distance(dive, landing);
+                        on_target
+                            && distance <= rules.keeper_reach as f32
+                            && rng.chance(save_chance(handling, distance, 1.0, &rules))
+                    }
+                    _ => false,
+                };
+                let scored = on_target && !saved;
+                shootout.record(team, scored);
+                events
+                    .events
+                    .push(MatchEvent::ShootoutKick { team, scored });
+
+                // Once all of a team have taken a kick they start again
+                shootout.taken.push(taker_id);
+                let all_taken = player_tiles
+                    .iter()
+                    .filter(|&&(_, _, other_team)| other_team == team)
+                    .all(|&(_, player_id, _)| shootout.taken.contains(&player_id));
+                if all_taken {
+                    shootout
+                        .taken
+                        .retain(|&player_id| players.get(player_id).unwrap().team != team);
+                }
+
+                turn.state = match shootout.winner(rules.shootout_kicks as usize) {
+                    Some(winner) => {
+                        events.events.push(MatchEvent::ShootoutWon { team: winner });
+                        TurnState::FullTime
+                    }
+                    None => TurnState::ShootoutDive {
+                        team: shootout.next_team().opponents(),
+                    },
+                };
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Plays out actions imported from an opponent's turn file, one at a time.
+pub struct ReplaySystem;
+
+impl<'a> System<'a> for ReplaySystem {
+    #![cfg_attr(feature = "cargo-clippy", allow(clippy::type_complexity))]
+    type SystemData = (
+        Entities<'a>,
+        Fetch<'a, Map>,
+        Fetch<'a, Rules>,
+        Fetch<'a, PendingRestart>,
+        FetchMut<'a, MatchEvents>,
+        FetchMut<'a, Notice>,
+        FetchMut<'a, Rng>,
+        FetchMut<'a, Shootout>,
+        FetchMut<'a, Turn>,
+        FetchMut<'a, TurnLog>,
+        ReadStorage<'a, TilePosition>,
+        ReadStorage<'a, Attributes>,
+        ReadStorage<'a, Bench>,
+        WriteStorage<'a, Player>,
+        WriteStorage<'a, Ball>,
+        ReadStorage<'a, Injury>,
+    );
+
+    fn run(&mut self, data: Self::SystemData) {
+        let (
+            entities,
+            map,
+            rules,
+            restart,
+            mut events,
+            mut notice,
+            mut rng,
+            mut shootout,
+            mut turn,
+            mut turn_log,
+            tile_positions,
+            attributes,
+            benched,
+            mut players,
+            mut balls,
+            injuries,
+        ) = data;
+
+        let idle = match turn.state {
+            TurnState::SelectPlayer
+            | TurnState::ShootoutDive { .. }
+            | TurnState::ShootoutTaker { .. }
+            | TurnState::ShootoutReveal { .. } => true,
+            _ => false,
+        };
+        if idle {
+            let action = match turn_log.replay.pop_front() {
+                None => return,
+                Some(action) => action,
+            };
+
+            // Only the side whose turn it is can act in it, so players are
+            // looked up among theirs
+            let side = turn.side;
+            let player_tiles = sorted_player_tiles(
+                (&*entities, &players, &tile_positions)
+                    .join()
+                    .filter(|&(player_id, _, _)| can_play(player_id, &injuries))
+                    .map(|(player_id, player, pos)| (pos.pos, player_id, player.team))
+                    .collect(),
+            );
+            let possessions: Vec<(Entity, Entity)> = (&*entities, &balls)
+                .join()
+                .filter_map(|(ball_id, ball)| match ball.state {
+                    BallState::Possessed { player_id } => Some((player_id, ball_id)),
+                    _ => None,
+                })
+                .collect();
+            let player_at = |pos: Point2<u32>| -> Option<Entity> {
+                player_tiles
+                    .iter()
+                    .find(|&&(tile, _, team)| tile == pos && team == side)
+                    .map(|&(_, player_id, _)| player_id)
+            };
+            let carrier_at = |pos: Point2<u32>| -> Option<(Entity, Entity)> {
+                let player_id = player_at(pos)?;
+                possessions
+                    .iter()
+                    .find(|&&(carrier_id, _)| carrier_id == player_id)
+                    .cloned()
+            };
+
+            let state = match action {
+                TurnAction::Run { from, path } => {
+                    player_at(from).map(|player_id| apply_run(player_id, path, &mut players))
+                }
+                TurnAction::Pass { from, path } => carrier_at(from).map(|(player_id, ball_id)| {
+                    apply_pass(
+                        player_id,
+                        ball_id,
+                        balls.get_mut(ball_id).unwrap(),
+                        path,
+                        &map,
+                        &rules,
+                        &restart,
+                        &mut events,
+                        &player_tiles,
+                    )
+                }),
+                TurnAction::LoftedPass { from, target } => {
+                    carrier_at(from).map(|(player_id, ball_id)| {
+                        apply_lofted_pass(
+                            player_id,
+                            ball_id,
+                            balls.get_mut(ball_id).unwrap(),
+                            from,
+                            target,
+                            attributes.get(player_id).unwrap().passing,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &restart,
+                            &mut events,
+                            &player_tiles,
+                        )
+                    })
+                }
+                TurnAction::ThroughBall {
+                    from,
+                    path,
+                    receiver,
+                } => match (carrier_at(from), player_at(receiver)) {
+                    (Some((player_id, ball_id)), Some(receiver_id)) => apply_through_ball(
+                        player_id,
+                        ball_id,
+                        balls.get_mut(ball_id).unwrap(),
+                        receiver_id,
+                        path,
+                        &map,
+                        &rules,
+                        &mut rng,
+                        &player_tiles,
+                        &mut players,
+                    ),
+                    _ => None,
+                },
+                TurnAction::KnockOn { from, path } => {
+                    carrier_at(from).map(|(player_id, ball_id)| {
+                        apply_knock_on(
+                            player_id,
+                            ball_id,
+                            balls.get_mut(ball_id).unwrap(),
+                            path,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &player_tiles,
+                            &mut players,
+                        )
+                    })
+                }
+                TurnAction::Shot { from, target } => {
+                    carrier_at(from).map(|(player_id, ball_id)| {
+                        apply_shot(
+                            player_id,
+                            ball_id,
+                            balls.get_mut(ball_id).unwrap(),
+                            target,
+                            attributes.get(player_id).unwrap().shooting,
+                            &map,
+                            &rules,
+                            &mut rng,
+                            &player_tiles,
+                        )
+                    })
+                }
+                TurnAction::Substitute { from, reserve } => {
+                    // Seriously injured players are out of play but can
+                    // still be replaced
+                    let replaced = (&*entities, &players, &tile_positions)
+                        .join()
+                        .find(|&(_, player, pos)| pos.pos == from && player.team == side)
+                        .map(|(player_id, _, _)| player_id);
+                    replaced.and_then(|player_id| {
+                        let bench = sorted_bench(
+                            (&*entities, &players, &benched)
+                                .join()
+                                .filter(|&(_, other, _)| other.team == side)
+                                .map(|(reserve_id, _, _)| reserve_id)
+                                .collect(),
+                        );
+                        apply_substitute(player_id, reserve, &bench)
+                    })
+                }
+                TurnAction::ShootoutDive { commitment } => match turn.state {
+                    TurnState::ShootoutDive { team } if team == side => {
+                        Some(apply_shootout_dive(team, commitment, &mut shootout))
+                    }
+                    _ => None,
+                },
+                TurnAction::ShootoutKick { from, target } => match turn.state {
+                    TurnState::ShootoutTaker { team } if team == side => player_at(from)
+                        .filter(|taker_id| !shootout.taken.contains(taker_id))
+                        .map(|taker_id| apply_shootout_kick(taker_id, target)),
+                    _ => None,
+                },
+                // Revealed by the side in goal, in their own turn
+                TurnAction::ShootoutReveal { dive, salt } => match turn.state {
+                    TurnState::ShootoutReveal { taker_id, target }
+                        if players.get(taker_id).unwrap().team != side =>
+                    {
+                        apply_shootout_reveal(taker_id, target, dive, salt, &mut shootout)
+                    }
+                    _ => None,
+                },
+            };
+            if let Some(state) = state {
+                turn.state = state;
+                return;
+            }
+
+            // The state hash matched, so this should only happen if the file
+            // describes an impossible turn, or moves the other side's players.
+            notice.show("Turn file action could not be replayed, abandoning replay".to_string());
+            turn_log.replay.clear();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use specs::World;
+
+    /// Plays out runs along `paths` a frame at a time, with each runner
+    /// taking as many steps in a frame as `frame_steps` gives them, and
+    /// returns who ran into whom.
+    fn collisions<F>(
+        paths: &[(Entity, PlayerTeam, Vec<Point2<u32>>)],
+        frame_steps: F,
+    ) -> Vec<(Entity, Entity)>
+    where
+        F: Fn(usize, usize) -> usize,
+    {
+        let mut trails: Vec<(Entity, PlayerTeam, Vec<Point2<u32>>)> = paths
+            .iter()
+            .map(|&(player_id, team, ref path)| (player_id, team, vec![path[0]]))
+            .collect();
+        let mut checked = 0;
+        let mut met = Vec::new();
+        for frame in 0.. {
+            let mut going = Vec::new();
+            for (runner, trail) in trails.iter_mut().enumerate() {
+                let path = &paths[runner].2;
+                let taken = trail.2.len();
+                let next = (taken + frame_steps(frame, runner)).min(path.len());
+                trail.2.extend_from_slice(&path[taken..next]);
+                going.push(next < path.len());
+            }
+            checked = meet_along_trails(&trails, &going, checked, &mut met);
+            if !going.contains(&true) {
+                break;
+            }
+        }
+        met
+    }
+
+    #[test]
+    fn collisions_do_not_depend_on_frame_length() {
+        let mut world = World::new();
+        let mut entity = || world.create_entity().build();
+        let (a, b, c, d, e, f) = (entity(), entity(), entity(), entity(), entity(), entity());
+        let tiles = |coords: &[(u32, u32)]| -> Vec<Point2<u32>> {
+            coords.iter().map(|&(x, y)| Point2::new(x, y)).collect()
+        };
+        let paths = vec![
+            // Head on, meeting on the same tile after two steps
+            (
+                a,
+                PlayerTeam::Red,
+                tiles(&[(1, 1), (2, 1), (3, 1), (4, 1), (5, 1)]),
+            ),
+            (
+                b,
+                PlayerTeam::Blue,
+                tiles(&[(5, 1), (4, 1), (3, 1), (2, 1), (1, 1)]),
+            ),
+            // Stopped on the tile the one coming the other way steps onto
+            (c, PlayerTeam::Red, tiles(&[(2, 3), (2, 2), (2, 1)])),
+            // Nowhere near anyone
+            (d, PlayerTeam::Blue, tiles(&[(7, 7), (7, 8), (7, 9)])),
+            // Swapping tiles in the first step
+            (e, PlayerTeam::Red, tiles(&[(8, 5), (9, 5)])),
+            (f, PlayerTeam::Blue, tiles(&[(9, 5), (8, 5)])),
+        ];
+        let expected = vec![(e, f), (a, b), (b, c)];
+
+        assert_eq!(collisions(&paths, |_, _| 1), expected);
+        assert_eq!(collisions(&paths, |_, _| 3), expected);
+        assert_eq!(collisions(&paths, |_, _| 100), expected);
+        assert_eq!(
+            collisions(&paths, |frame, runner| (frame + runner) % 3),
+            expected
+        );
+    }
+}
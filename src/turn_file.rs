@@ -0,0 +1,504 @@
+//! Play-by-mail turn files.
+//!
+//! A turn file records the hash of the game state before the turn, followed
+//! by the actions taken during it, and ends with a checksum of its own
+//! contents to catch files damaged on the way. The opponent imports the file,
+//! checks the state hash against their own game and replays the actions
+//! through the usual systems, refusing any which move the wrong side's
+//! players. Both hashes are checks that the two games haven't drifted apart,
+//! not protection against cheating: anyone can edit a turn and work out the
+//! hashes again. A goalkeeper's dive in a penalty shootout is only written
+//! as a commitment until the kick has been aimed, so that it isn't on show
+//! in the file the taker imports.
+
+use std::fmt;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::Path;
+
+use nalgebra::Point2;
+use specs::{Join, World};
+
+use components::{Ball, BallState, Player, PlayerTeam, TilePosition};
+use conditions::Conditions;
+use resources::{
+    MatchClock, PendingRestart, Rng, Score, Shootout, Substitutions, Turn, TurnAction, TurnLog,
+};
+use rules::Rules;
+
+const HEADER: &str = "tbf-turn 1";
+
+#[derive(Debug)]
+pub enum TurnFileError {
+    Io(io::Error),
+    Malformed { line: usize },
+    BadChecksum,
+    StateMismatch { expected: u64, actual: u64 },
+}
+
+impl fmt::Display for TurnFileError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            TurnFileError::Io(ref err) => write!(f, "{}", err),
+            TurnFileError::Malformed { line } => write!(f, "malformed turn file at line {}", line),
+            TurnFileError::BadChecksum => write!(f, "turn file checksum does not match"),
+            TurnFileError::StateMismatch { expected, actual } => write!(
+                f,
+                "turn was played from state {:016x} but this game is at {:016x}",
+                expected, actual
+            ),
+        }
+    }
+}
+
+impl From<io::Error> for TurnFileError {
+    fn from(err: io::Error) -> Self {
+        TurnFileError::Io(err)
+    }
+}
+
+// FNV-1a, chosen because it is stable across platforms and compiler versions.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in bytes {
+        hash ^= u64::from(*byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+/// Commits to a goalkeeper's `dive` without giving it away, since with a
+/// random `salt` every dive is as likely to have made any commitment.
+pub fn dive_commitment(dive: Point2<u32>, salt: u64) -> u64 {
+    fnv1a(format!("{},{},{:016x}", dive.x, dive.y, salt).as_bytes())
+}
+
+/// Hashes everything about the game state which actions depend upon.
+pub fn state_hash(world: &World) -> u64 {
+    let players = world.read::<Player>();
+    let balls = world.read::<Ball>();
+    let tile_positions = world.read::<TilePosition>();
+
+    let mut player_states: Vec<(u8, u32, u32)> = (&players, &tile_positions)
+        .join()
+        .map(|(player, pos)| {
+            let team = match player.team {
+                PlayerTeam::Red => 0,
+                PlayerTeam::Blue => 1,
+            };
+            (team, pos.pos.x, pos.pos.y)
+        })
+        .collect();
+    player_states.sort();
+
+    let mut state = String::new();
+    for (team, x, y) in player_states {
+        state.push_str(&format!("p{},{},{};", team, x, y));
+    }
+    for (ball, pos) in (&balls, &tile_positions).join() {
+        state.push_str(&format!("b{},{}", pos.pos.x, pos.pos.y));
+        if let BallState::Possessed { player_id } = ball.state {
+            if let Some(player_pos) = tile_positions.get(player_id) {
+                state.push_str(&format!("@{},{}", player_pos.pos.x, player_pos.pos.y));
+            }
+        }
+        state.push(';');
+    }
+    // Whoever is waiting to take a restart is the only one who can play
+    if let Some((taker_id, _)) = world.read_resource::<PendingRestart>().taker {
+        if let Some(taker_pos) = tile_positions.get(taker_id) {
+            state.push_str(&format!("t{},{};", taker_pos.pos.x, taker_pos.pos.y));
+        }
+    }
+    let score = world.read_resource::<Score>();
+    state.push_str(&format!("s{},{};", score.red, score.blue));
+    let subs = world.read_resource::<Substitutions>();
+    state.push_str(&format!("u{},{};", subs.red, subs.blue));
+    let clock = world.read_resource::<MatchClock>();
+    state.push_str(&format!("c{},{};", clock.half, clock.elapsed));
+    let shootout = world.read_resource::<Shootout>();
+    let kicks = |taken: &[bool]| {
+        taken
+            .iter()
+            .map(|&scored| if scored { '1' } else { '0' })
+            .collect::<String>()
+    };
+    state.push_str(&format!(
+        "k{},{};",
+        kicks(&shootout.red),
+        kicks(&shootout.blue)
+    ));
+    // Only the side who chose the dive knows it, so it goes in as the
+    // commitment both have
+    if let Some(commitment) = shootout.commitment {
+        state.push_str(&format!("d{:016x};", commitment));
+    }
+    let conditions = world.read_resource::<Conditions>();
+    state.push_str(&format!(
+        "w{},{},{};",
+        conditions.weather.name(),
+        conditions.wind.x,
+        conditions.wind.y
+    ));
+    // Both games have to agree on whose turn it is
+    let side = match world.read_resource::<Turn>().side {
+        PlayerTeam::Red => 0,
+        PlayerTeam::Blue => 1,
+    };
+    state.push_str(&format!("v{};", side));
+    // Games played by different rules soon go their separate ways
+    state.push_str(&format!("x{};", world.read_resource::<Rules>().summary()));
+    // Outcomes of later actions depend on the random number generator
+    state.push_str(&format!("r{:016x};", world.read_resource::<Rng>().state));
+
+    fnv1a(state.as_bytes())
+}
+
+fn format_tiles(from: Point2<u32>, path: &[Point2<u32>]) -> String {
+    let mut line = format!("{},{}", from.x, from.y);
+    for step in path {
+        line.push_str(&format!(" {},{}", step.x, step.y));
+    }
+    line
+}
+
+fn parse_tile(word: &str) -> Option<Point2<u32>> {
+    let mut coords = word.split(',');
+    let x = coords.next()?.parse().ok()?;
+    let y = coords.next()?.parse().ok()?;
+    if coords.next().is_some() {
+        return None;
+    }
+    Some(Point2::new(x, y))
+}
+
+fn parse_action(line: &str) -> Option<TurnAction> {
+    let mut words = line.split_whitespace();
+    let kind = words.next()?;
+    if kind == "sub" {
+        let from = parse_tile(words.next()?)?;
+        let reserve = words.next()?.parse().ok()?;
+        if words.next().is_some() {
+            return None;
+        }
+        return Some(TurnAction::Substitute { from, reserve });
+    }
+    if kind == "dive" {
+        let commitment = u64::from_str_radix(words.next()?, 16).ok()?;
+        if words.next().is_some() {
+            return None;
+        }
+        return Some(TurnAction::ShootoutDive { commitment });
+    }
+    if kind == "reveal" {
+        let dive = parse_tile(words.next()?)?;
+        let salt = u64::from_str_radix(words.next()?, 16).ok()?;
+        if words.next().is_some() {
+            return None;
+        }
+        return Some(TurnAction::ShootoutReveal { dive, salt });
+    }
+    let receiver = if kind == "through" {
+        Some(parse_tile(words.next()?)?)
+    } else {
+        None
+    };
+    let from = parse_tile(words.next()?)?;
+    let path = words.map(parse_tile).collect::<Option<Vec<_>>>()?;
+    if path.is_empty() {
+        return None;
+    }
+
+    match kind {
+        "run" => Some(TurnAction::Run { from, path }),
+        "pass" => Some(TurnAction::Pass { from, path }),
+        "knock" => Some(TurnAction::KnockOn { from, path }),
+        "loft" if path.len() == 1 => Some(TurnAction::LoftedPass {
+            from,
+            target: path[0],
+        }),
+        "shoot" if path.len() == 1 => Some(TurnAction::Shot {
+            from,
+            target: path[0],
+        }),
+        "penalty" if path.len() == 1 => Some(TurnAction::ShootoutKick {
+            from,
+            target: path[0],
+        }),
+        "through" => Some(TurnAction::ThroughBall {
+            from,
+            path,
+            receiver: receiver?,
+        }),
+        _ => None,
+    }
+}
+
+fn format_action(action: &TurnAction) -> String {
+    match *action {
+        TurnAction::Run { from, ref path } => format!("run {}", format_tiles(from, path)),
+        TurnAction::Pass { from, ref path } => format!("pass {}", format_tiles(from, path)),
+        TurnAction::KnockOn { from, ref path } => format!("knock {}", format_tiles(from, path)),
+        TurnAction::LoftedPass { from, target } => {
+            format!("loft {}", format_tiles(from, &[target]))
+        }
+        TurnAction::Shot { from, target } => format!("shoot {}", format_tiles(from, &[target])),
+        TurnAction::ShootoutDive { commitment } => format!("dive {:016x}", commitment),
+        TurnAction::ShootoutKick { from, target } => {
+            format!("penalty {}", format_tiles(from, &[target]))
+        }
+        TurnAction::ShootoutReveal { dive, salt } => {
+            format!("reveal {} {:016x}", format_tiles(dive, &[]), salt)
+        }
+        TurnAction::Substitute { from, reserve } => {
+            format!("sub {} {}", format_tiles(from, &[]), reserve)
+        }
+        TurnAction::ThroughBall {
+            from,
+            ref path,
+            receiver,
+        } => format!(
+            "through {},{} {}",
+            receiver.x,
+            receiver.y,
+            format_tiles(from, path)
+        ),
+    }
+}
+
+/// Writes the actions taken this turn to `path` and starts a new turn.
+pub fn export_turn(world: &mut World, path: &Path) -> Result<(), TurnFileError> {
+    let mut contents = format!("{}\n", HEADER);
+    {
+        let log = world.read_resource::<TurnLog>();
+        contents.push_str(&format!("state {:016x}\n", log.start_hash));
+        for action in &log.actions {
+            contents.push_str(&format_action(action));
+            contents.push('\n');
+        }
+    }
+    let checksum = fnv1a(contents.as_bytes());
+    contents.push_str(&format!("hash {:016x}\n", checksum));
+
+    let mut file = File::create(path)?;
+    file.write_all(contents.as_bytes())?;
+
+    // The turn now belongs to the other side
+    let side = world.read_resource::<Turn>().side;
+    world.write_resource::<Turn>().side = side.opponents();
+    let hash = state_hash(world);
+    world.write_resource::<TurnLog>().reset(hash);
+
+    Ok(())
+}
+
+/// Reads the opponent's turn from `path`, validates it against the current
+/// game state and queues its actions for replay.
+pub fn import_turn(world: &mut World, path: &Path) -> Result<(), TurnFileError> {
+    let mut contents = String::new();
+    File::open(path)?.read_to_string(&mut contents)?;
+
+    // The checksum covers everything up to and including the newline before it.
+    let hash_start = contents
+        .rfind("hash ")
+        .ok_or(TurnFileError::Malformed { line: 1 })?;
+    let (body, hash_line) = contents.split_at(hash_start);
+    let checksum = u64::from_str_radix(hash_line["hash ".len()..].trim(), 16).map_err(|_| {
+        TurnFileError::Malformed {
+            line: body.lines().count() + 1,
+        }
+    })?;
+    if fnv1a(body.as_bytes()) != checksum {
+        return Err(TurnFileError::BadChecksum);
+    }
+
+    let mut lines = body.lines();
+    if lines.next() != Some(HEADER) {
+        return Err(TurnFileError::Malformed { line: 1 });
+    }
+    let expected = lines
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|hash| u64::from_str_radix(hash, 16).ok())
+        .ok_or(TurnFileError::Malformed { line: 2 })?;
+
+    let mut actions = Vec::new();
+    for (i, line) in lines.enumerate() {
+        actions.push(parse_action(line).ok_or(TurnFileError::Malformed { line: i + 3 })?);
+    }
+
+    let actual = state_hash(world);
+    if expected != actual {
+        return Err(TurnFileError::StateMismatch { expected, actual });
+    }
+
+    let mut log = world.write_resource::<TurnLog>();
+    log.replay.extend(actions);
+    log.replaying = true;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use components::PlayerState;
+    use resources::TurnState;
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+    }
+
+    #[test]
+    fn actions_survive_a_round_trip() {
+        let from = Point2::new(3, 4);
+        let path = vec![Point2::new(4, 4), Point2::new(5, 5)];
+        let actions = vec![
+            TurnAction::Run {
+                from,
+                path: path.clone(),
+            },
+            TurnAction::Pass {
+                from,
+                path: path.clone(),
+            },
+            TurnAction::LoftedPass {
+                from,
+                target: Point2::new(10, 2),
+            },
+            TurnAction::ThroughBall {
+                from,
+                path: path.clone(),
+                receiver: Point2::new(6, 7),
+            },
+            TurnAction::KnockOn {
+                from,
+                path: path.clone(),
+            },
+            TurnAction::Shot {
+                from,
+                target: Point2::new(20, 6),
+            },
+            TurnAction::Substitute { from, reserve: 2 },
+            TurnAction::ShootoutDive {
+                commitment: 0x0123_4567_89ab_cdef,
+            },
+            TurnAction::ShootoutKick {
+                from,
+                target: Point2::new(20, 7),
+            },
+            TurnAction::ShootoutReveal {
+                dive: Point2::new(20, 5),
+                salt: 0xfedc_ba98_7654_3210,
+            },
+        ];
+        for action in actions {
+            assert_eq!(parse_action(&format_action(&action)), Some(action));
+        }
+    }
+
+    #[test]
+    fn malformed_actions_are_rejected() {
+        assert_eq!(parse_action(""), None);
+        assert_eq!(parse_action("run 3,4"), None);
+        assert_eq!(parse_action("walk 3,4 4,4"), None);
+        assert_eq!(parse_action("shoot 3,4 4,4 5,5"), None);
+        assert_eq!(parse_action("dive 3,4"), None);
+        assert_eq!(parse_action("reveal 3,4"), None);
+        assert_eq!(parse_action("reveal 3,4 12ab 5"), None);
+        assert_eq!(parse_action("pass 3,4 4"), None);
+    }
+
+    fn world_with_players(players: &[(PlayerTeam, Point2<u32>)]) -> World {
+        let mut world = World::new();
+        world.register::<Player>();
+        world.register::<Ball>();
+        world.register::<TilePosition>();
+        world.add_resource(PendingRestart::default());
+        world.add_resource(Score::default());
+        world.add_resource(Substitutions::default());
+        world.add_resource(MatchClock::new());
+        world.add_resource(Shootout::default());
+        world.add_resource(Conditions::default());
+        world.add_resource(Rules::default());
+        world.add_resource(Rng::new(1));
+        world.add_resource(Turn {
+            state: TurnState::SelectPlayer,
+            side: PlayerTeam::Red,
+        });
+        for &(team, pos) in players {
+            world
+                .create_entity()
+                .with(Player {
+                    state: PlayerState::Still,
+                    team,
+                })
+                .with(TilePosition { pos })
+                .build();
+        }
+        world
+            .create_entity()
+            .with(Ball {
+                state: BallState::Free,
+                last_touch: PlayerTeam::Red,
+            })
+            .with(TilePosition {
+                pos: Point2::new(5, 5),
+            })
+            .build();
+        world
+    }
+
+    #[test]
+    fn state_hash_is_stable() {
+        let red = (PlayerTeam::Red, Point2::new(2, 3));
+        let blue = (PlayerTeam::Blue, Point2::new(8, 3));
+        let hash = state_hash(&world_with_players(&[red, blue]));
+        assert_eq!(state_hash(&world_with_players(&[red, blue])), hash);
+        assert_eq!(state_hash(&world_with_players(&[blue, red])), hash);
+    }
+
+    #[test]
+    fn state_hash_changes_with_the_state() {
+        let red = (PlayerTeam::Red, Point2::new(2, 3));
+        let blue = (PlayerTeam::Blue, Point2::new(8, 3));
+        let hash = state_hash(&world_with_players(&[red, blue]));
+        let moved = (PlayerTeam::Red, Point2::new(2, 4));
+        assert_ne!(state_hash(&world_with_players(&[moved, blue])), hash);
+
+        let world = world_with_players(&[red, blue]);
+        world.write_resource::<Score>().red = 1;
+        assert_ne!(state_hash(&world), hash);
+
+        let world = world_with_players(&[red, blue]);
+        world.write_resource::<Rng>().state += 1;
+        assert_ne!(state_hash(&world), hash);
+
+        let world = world_with_players(&[red, blue]);
+        world.write_resource::<Turn>().side = PlayerTeam::Blue;
+        assert_ne!(state_hash(&world), hash);
+    }
+
+    #[test]
+    fn state_hash_keeps_the_dive_hidden() {
+        let red = (PlayerTeam::Red, Point2::new(2, 3));
+        let blue = (PlayerTeam::Blue, Point2::new(8, 3));
+        let dive = Point2::new(20, 5);
+        let world = world_with_players(&[red, blue]);
+        world.write_resource::<Shootout>().commitment = Some(dive_commitment(dive, 7));
+        let hash = state_hash(&world);
+        {
+            let mut shootout = world.write_resource::<Shootout>();
+            shootout.dive = Some(dive);
+            shootout.salt = 7;
+        }
+        assert_eq!(state_hash(&world), hash);
+        assert_ne!(
+            dive_commitment(dive, 7),
+            dive_commitment(Point2::new(20, 6), 7)
+        );
+    }
+}